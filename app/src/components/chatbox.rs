@@ -1,7 +1,150 @@
+use common::message::{ChatContent, ChatEntry};
 use leptos::*;
 use tracing::info;
+use uuid::Uuid;
+use wasm_bindgen::JsValue;
+use web_sys::js_sys::Date;
 
-use crate::{components::portal::Portal, networking::room_manager::RoomManager, MountPoints};
+use crate::{
+    components::portal::Portal,
+    networking::room_manager::RoomManager,
+    utils::{
+        chat_command::{is_mention_of, mentions, tokenize, ChatToken},
+        profile::user_color,
+    },
+    MountPoints,
+};
+
+/// Local `HH:MM` of a server timestamp.
+fn format_time(sent_at: u64) -> String {
+    let date = Date::new(&JsValue::from_f64(sent_at as f64));
+    format!("{:02}:{:02}", date.get_hours(), date.get_minutes())
+}
+
+/// Message text with emotes as images and mentions highlighted.
+fn chat_text(text: &str, own_name: &str) -> View {
+    tokenize(text)
+        .into_iter()
+        .map(|token| match token {
+            ChatToken::Text(text) => text.to_string().into_view(),
+            ChatToken::Emote(name) => view! {
+                <img
+                    class="inline h-5 w-5 align-text-bottom"
+                    src=format!("/assets/emotes/{name}.svg")
+                    alt=format!(":{name}:")
+                    title=format!(":{name}:")
+                />
+            }
+            .into_view(),
+            ChatToken::Mention(mention) => view! {
+                <span
+                    class="text-yellow-300"
+                    class=("underline", is_mention_of(mention, own_name))
+                >
+                    "@"
+                    {mention.to_string()}
+                </span>
+            }
+            .into_view(),
+        })
+        .collect_view()
+}
+
+#[component]
+fn ChatLine(entry: ChatEntry) -> impl IntoView {
+    let room_info = expect_context::<RoomManager>().get_room_info();
+    // Authors that left keep the default text color
+    let author_color = move |user_id: Uuid| {
+        move || {
+            room_info.with(|r| {
+                r.as_ref()
+                    .and_then(|r| r.users.iter().find(|u| u.id == user_id))
+                    .map(user_color)
+            })
+        }
+    };
+    let own_name = room_info
+        .with_untracked(|r| {
+            r.as_ref()
+                .and_then(|r| r.users.iter().find(|u| u.id == r.user_id))
+                .map(|u| u.name.clone())
+        })
+        .unwrap_or_default();
+    let time = format_time(entry.sent_at);
+    let system = |text: String| {
+        view! { <span class="italic text-gray-400"> {text} </span> }.into_view()
+    };
+    let is_mentioned = match &entry.content {
+        ChatContent::Message { text, .. } | ChatContent::Action { text, .. } => {
+            mentions(text, &own_name)
+        }
+        _ => false,
+    };
+    let content = match entry.content {
+        ChatContent::Message {
+            user_id,
+            name,
+            text,
+        } => view! {
+            <span class="font-thin8 text-md" style:color=author_color(user_id)>
+                {name}
+            </span>
+            <span class="font-thin8 text-md"> ": " </span>
+            <span> {chat_text(&text, &own_name)} </span>
+        }
+        .into_view(),
+        ChatContent::Action {
+            user_id,
+            name,
+            text,
+        } => view! {
+            <span class="italic">
+                "* " <span style:color=author_color(user_id)> {name} </span> " "
+                {chat_text(&text, &own_name)}
+            </span>
+        }
+        .into_view(),
+        ChatContent::Roll {
+            name,
+            roll,
+            results,
+        } => {
+            let rolled = results
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(" + ");
+            let total = results.iter().sum::<u32>();
+            if results.len() > 1 {
+                system(format!("{name} rolled {roll}: {rolled} = {total}"))
+            } else {
+                system(format!("{name} rolled {roll}: {total}"))
+            }
+        }
+        ChatContent::UserJoined(name) => system(format!("{name} joined the room")),
+        ChatContent::SpectatorJoined(name) => {
+            system(format!("{name} joined the full room as a spectator"))
+        }
+        ChatContent::Promoted(name) => system(format!("{name} took a free player spot")),
+        ChatContent::UserLeft(name) => system(format!("{name} left the room")),
+        ChatContent::HostChanged(name) => system(format!("{name} is now the host")),
+        ChatContent::UserKicked(name) => system(format!("{name} was kicked from the room")),
+        ChatContent::Renamed { from, to } => system(format!("{from} is now called {to}")),
+        ChatContent::SlotClaimed {
+            name,
+            slot: Some(slot),
+        } => system(format!("{name} plays as player {slot}")),
+        ChatContent::SlotClaimed { name, slot: None } => {
+            system(format!("{name} left their player slot"))
+        }
+    };
+    view! {
+        <div class="w-full text-md font-thin14" class=("bg-yellow-900", is_mentioned)>
+            <span class="text-xs text-gray-500 mr-1"> {time} </span>
+            {content}
+        </div>
+    }
+}
 
 #[component]
 pub fn ChatBox() -> impl IntoView {
@@ -17,6 +160,24 @@ pub fn ChatBox() -> impl IntoView {
             set_msg_len.set(message_history.with_value(|v| v.len()));
         });
         let (chat_msg, set_chat_msg) = create_signal(String::new());
+        let server_error = room_manager.get_server_error();
+        let mentioned = room_manager.get_mentioned();
+        let (chat_error, set_chat_error) = create_signal(None::<String>);
+        let error = move || {
+            chat_error
+                .get()
+                .or_else(|| server_error.get().map(|err| err.to_string()))
+        };
+        let submit = move || {
+            let rm = expect_context::<RoomManager>();
+            match rm.send_chat(chat_msg.get_untracked()) {
+                Ok(()) => {
+                    set_chat_error.set(None);
+                    set_chat_msg.set(String::new());
+                }
+                Err(err) => set_chat_error.set(Some(err)),
+            }
+        };
 
         view! {
             {
@@ -37,7 +198,9 @@ pub fn ChatBox() -> impl IntoView {
                                     <div class="text-center w-full"> "Chat" </div>
                                     // <hr class="border-white border-t w-full" />
     
-                                    <div class="flex-grow overflow-auto h-full w-full flex flex-col-reverse">
+                                    <div class="flex-grow overflow-auto h-full w-full flex flex-col-reverse"
+                                        style:box-shadow=move || mentioned.get().then_some("inset 0 0 0 2px #facc15")
+                                    >
                                         <For
                                             each=move||{
                                                 let len =msg_len.get();
@@ -45,39 +208,33 @@ pub fn ChatBox() -> impl IntoView {
                                             }
                                             key=|i|*i
                                             children = move|i|{
-                                                let msg = message_history.with_value(|v|v.get(i).cloned());
-                                                if let Some((user,msg)) = msg {
-                                                    view! {
-                                                        <div class="w-full text-md font-thin14">
-                                                            <span class="font-thin8 text-md"> {user.name} ": " </span>
-                                                            <span>
-                                                                {msg}
-                                                            </span>
-                                                        </div>
-                                                    }.into_view()
+                                                let entry = message_history.with_value(|v|v.get(i).cloned());
+                                                if let Some(entry) = entry {
+                                                    view! { <ChatLine entry /> }.into_view()
                                                 }else{
                                                     view! {}.into_view()
                                                 }
                                             }
                                         />
                                     </div>
+                                    <Show when=move || error().is_some()>
+                                        <div class="w-full px-2 text-sm text-red-400">
+                                            {error}
+                                        </div>
+                                    </Show>
                                     <form class="w-full flex"
                                         on:submit=move|ev|{
                                             ev.prevent_default();
-                                            let rm = expect_context::<RoomManager>();
-                                            rm.send_chat(chat_msg.get_untracked());
-                                            set_chat_msg.set(String::new());
+                                            submit();
                                         }
                                     >
-                                        <input class="w-full text-md font-thin16 p-2 bg-transparent text-white" placeholder="Enter msg to chat"
+                                        <input class="w-full text-md font-thin16 p-2 bg-transparent text-white" placeholder="Enter msg to chat, /help for commands"
                                             on:input=move|ev| {
                                                 set_chat_msg.set(event_target_value(&ev))
                                             }
                                             on:keyup=move|ev| {
                                                 if ev.key_code() == 13 || ev.key() == "Enter" {
-                                                    let rm = expect_context::<RoomManager>();
-                                                    rm.send_chat(chat_msg.get_untracked());
-                                                    set_chat_msg.set(String::new());
+                                                    submit();
                                                 }
                                             }
                                             prop:value=chat_msg
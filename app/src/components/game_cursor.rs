@@ -0,0 +1,125 @@
+use common::message::UserCursor;
+use leptos::*;
+use uuid::Uuid;
+use web_sys::Element;
+
+use crate::{
+    networking::room_manager::RoomManager,
+    utils::{
+        coordinates::Rect,
+        profile::{display_name, user_color},
+    },
+};
+
+/// Whether `el` holds the pointer lock, mouse input is relative then.
+pub fn is_pointer_locked(el: &Element) -> bool {
+    document().pointer_lock_element().as_ref() == Some(el)
+}
+
+/// Button locking the pointer to `target` for games using mouse look,
+/// Escape gives it back.
+#[component]
+pub fn PointerLockButton(#[prop(into)] target: Signal<Option<Element>>) -> impl IntoView {
+    view! {
+        <button
+            type="button"
+            class="px-1 text-xs bg-black/60 hover:bg-black/80 pointer-events-auto"
+            title="Capture the mouse for games that use mouse look, press Escape to release it"
+            on:click=move |_| {
+                if let Some(target) = target.get_untracked() {
+                    target.request_pointer_lock();
+                }
+            }
+        >
+            "Lock mouse"
+        </button>
+    }
+}
+
+/// Draws the host's virtual cursor over the game. `area` is where the game
+/// is shown, relative to the overlay's parent.
+#[component]
+pub fn GameCursor(
+    #[prop(into)] position: Signal<Option<(f64, f64)>>,
+    #[prop(into)] area: Signal<Rect>,
+) -> impl IntoView {
+    view! {
+        <Show when=move || position.with(Option::is_some)>
+            <div
+                class="absolute z-10 w-3 h-3 -ml-1.5 -mt-1.5 rounded-full border-2 border-black bg-white pointer-events-none"
+                style=move || {
+                    let (x, y) = position.get().unwrap_or_default();
+                    let area = area.get();
+                    format!(
+                        "left: {}px; top: {}px;",
+                        area.left + x * area.width,
+                        area.top + y * area.height,
+                    )
+                }
+            />
+        </Show>
+    }
+}
+
+/// Draws where the other players point, labeled with their names. `area` is
+/// where the game is shown, relative to the overlay's parent.
+#[component]
+pub fn RemoteCursors(
+    #[prop(into)] cursors: Signal<Vec<UserCursor>>,
+    #[prop(into)] area: Signal<Rect>,
+) -> impl IntoView {
+    let room_info = expect_context::<RoomManager>().get_room_info();
+    let others = move || {
+        let own_id = room_info.with(|r| r.as_ref().map(|r| r.user_id));
+        cursors
+            .get()
+            .into_iter()
+            .filter(|c| Some(c.user_id) != own_id)
+            .collect::<Vec<_>>()
+    };
+    let user = move |user_id: Uuid| {
+        room_info.with(|r| {
+            r.as_ref()
+                .and_then(|r| r.users.iter().find(|u| u.id == user_id))
+                .cloned()
+        })
+    };
+
+    view! {
+        <For
+            each=others
+            key=|cursor| cursor.user_id
+            children=move |cursor| {
+                let user_id = cursor.user_id;
+                let color = move || user(user_id).map(|u| user_color(&u)).unwrap_or_default();
+                view! {
+                    <div
+                        class="absolute z-10 flex items-start pointer-events-none"
+                        style=move || {
+                            let (x, y) = cursors
+                                .with(|c| c.iter().find(|c| c.user_id == user_id).map(|c| c.position))
+                                .unwrap_or_default();
+                            let area = area.get();
+                            format!(
+                                "left: {}px; top: {}px;",
+                                area.left + x * area.width,
+                                area.top + y * area.height,
+                            )
+                        }
+                    >
+                        <div
+                            class="w-2.5 h-2.5 -ml-1 -mt-1 rounded-full border border-black"
+                            style:background-color=color
+                        />
+                        <div
+                            class="ml-1 px-1 text-xs text-black rounded whitespace-nowrap"
+                            style:background-color=color
+                        >
+                            {move || user(user_id).map(|u| display_name(&u)).unwrap_or_default()}
+                        </div>
+                    </div>
+                }
+            }
+        />
+    }
+}
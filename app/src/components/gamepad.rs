@@ -1,14 +1,25 @@
+use std::{cell::Cell, collections::HashSet, time::Duration};
+
 use ::svg::node::{element::path::Data, Value};
+use common::UserState;
 use leptos::*;
+use leptos_use::{use_event_listener, use_window_size, UseWindowSizeReturn};
 use strum::IntoEnumIterator;
-use tracing::info;
+use tracing::{info, warn};
 use uuid::Uuid;
 use wasm_bindgen::JsCast;
-use web_sys::Element;
+use web_sys::{js_sys::Date, Element};
 
 use crate::{
     components::{icons::Icon, touchmanager::TouchManager},
-    utils::keycode::{Key, KeyEvent},
+    networking::room_manager::RoomManager,
+    utils::{
+        gamepad_layout::{
+            ButtonKind, ButtonLayout, GamepadLayout, MacroStep, Orientation, DEFAULT_TURBO_RATE,
+        },
+        gamepad_mapping::stick_keys,
+        keycode::{key_char, Key, KeyEvent, MouseButton},
+    },
 };
 
 #[derive(Clone)]
@@ -25,81 +36,117 @@ struct GamepadButton {
 enum GamepadButtonType {
     Dpad(RwSignal<(Key, Key, Key, Key)>),
     Button(RwSignal<Key>),
+    /// Analog style stick holding its direction keys past a threshold
+    Joystick(RwSignal<(Key, Key, Key, Key)>),
+    /// Area moving the game's mouse, tap to click
+    Trackpad,
+    /// Press once to hold the key, again to release it
+    Toggle(RwSignal<Key>),
+    /// Repeatedly taps the key while held, rate in presses per second
+    Turbo(RwSignal<Key>, RwSignal<u32>),
+    /// Plays back a recorded sequence of events
+    Macro(RwSignal<Vec<MacroStep>>),
+}
+
+/// How a [`SingleButton`] turns touches into key presses.
+#[derive(Clone, Copy)]
+enum ButtonBehavior {
+    Hold,
+    Toggle,
+    /// Presses per second
+    Turbo(RwSignal<u32>),
 }
 
+const MAX_TURBO_RATE: u32 = 30;
+const MAX_MACRO_STEPS: usize = 200;
+
 #[component]
 pub fn Gamepad(keys_sender: WriteSignal<Option<KeyEvent>>) -> impl IntoView {
     let (key_rx, key_tx) = create_signal(None);
 
     let (selected_button, set_selected_button) = create_signal(None);
-    let (gamebuttons, set_game_buttons) = create_signal(vec![
-        GamepadButton {
-            id: uuid::Uuid::new_v4(),
-            position: RwSignal::new((10.0, 60.0)),
-            translation: RwSignal::new((0.0, 0.0)),
-            width: RwSignal::new(20.0),
-            scale: RwSignal::new(1.0),
-            button: GamepadButtonType::Dpad(RwSignal::new((Key::A, Key::D, Key::W, Key::S))),
-        },
-        GamepadButton {
-            id: uuid::Uuid::new_v4(),
-            position: RwSignal::new((80.0, 70.0)),
-            translation: RwSignal::new((0.0, 0.0)),
-            width: RwSignal::new(5.0),
-            scale: RwSignal::new(1.0),
-            button: GamepadButtonType::Button(RwSignal::new(Key::Z)),
-        },
-        GamepadButton {
-            id: uuid::Uuid::new_v4(),
-            position: RwSignal::new((90.0, 70.0)),
-            translation: RwSignal::new((0.0, 0.0)),
-            width: RwSignal::new(5.0),
-            scale: RwSignal::new(1.0),
-            button: GamepadButtonType::Button(RwSignal::new(Key::X)),
-        },
-        GamepadButton {
-            id: uuid::Uuid::new_v4(),
-            position: RwSignal::new((85.0, 60.0)),
-            translation: RwSignal::new((0.0, 0.0)),
-            width: RwSignal::new(5.0),
-            scale: RwSignal::new(1.0),
-            button: GamepadButtonType::Button(RwSignal::new(Key::C)),
-        },
-        GamepadButton {
-            id: uuid::Uuid::new_v4(),
-            position: RwSignal::new((85.0, 80.0)),
-            translation: RwSignal::new((0.0, 0.0)),
-            width: RwSignal::new(5.0),
-            scale: RwSignal::new(1.0),
-            button: GamepadButtonType::Button(RwSignal::new(Key::V)),
-        },
-        GamepadButton {
-            id: uuid::Uuid::new_v4(),
-            position: RwSignal::new((75.0, 82.5)),
-            translation: RwSignal::new((0.0, 0.0)),
-            width: RwSignal::new(5.0),
-            scale: RwSignal::new(1.0),
-            button: GamepadButtonType::Button(RwSignal::new(Key::Space)),
-        },
-        GamepadButton {
-            id: uuid::Uuid::new_v4(),
-            position: RwSignal::new((92.5, 50.0)),
-            translation: RwSignal::new((0.0, 0.0)),
-            width: RwSignal::new(5.0),
-            scale: RwSignal::new(1.0),
-            button: GamepadButtonType::Button(RwSignal::new(Key::Enter)),
-        },
-    ]);
+    let (gamebuttons, set_game_buttons) =
+        create_signal(buttons_from_layout(&GamepadLayout::default()));
+
+    let room_manager = expect_context::<RoomManager>();
+    let room_info = room_manager.get_room_info();
+    let game = create_memo(move |_| {
+        room_info.with(|r| {
+            r.as_ref()
+                .and_then(|r| r.users.first())
+                .and_then(|host| match &host.state {
+                    UserState::VideoSelected(name) => Some(name.clone()),
+                    UserState::VideoNotSelected => None,
+                })
+        })
+    });
+    let is_host = create_memo(move |_| room_info.with(|r| r.as_ref().is_some_and(|r| r.is_host)));
+    let recommended_layout = create_memo(move |_| {
+        room_info.with(|r| {
+            r.as_ref()
+                .and_then(|r| r.recommended_layout.as_ref())
+                .and_then(|code| GamepadLayout::from_code(code).ok())
+        })
+    });
+    let UseWindowSizeReturn { width, height } = use_window_size();
+    let orientation = create_memo(move |_| Orientation::from_size(width.get(), height.get()));
 
     let (is_editing_mode, set_is_editing_mode) = create_signal(false);
+    // Game and orientation the layout being edited is saved under
+    let edit_target = store_value(None::<(Option<String>, Orientation)>);
+
+    // Saved layout for this game and orientation, else the host's recommendation.
+    // Left alone while editing so unsaved changes aren't lost
+    create_effect(move |_| {
+        if is_editing_mode.get() {
+            return;
+        }
+        let layout = GamepadLayout::load(game.get().as_deref(), orientation.get())
+            .or_else(|| recommended_layout.get())
+            .unwrap_or_default();
+        set_game_buttons.set(buttons_from_layout(&layout));
+    });
+
     let (is_enabled, set_is_enabled) = create_signal(false);
+    // Macro button being recorded, fed by the gamepad and the keyboard
+    let recording = create_rw_signal(Option::<RwSignal<Vec<MacroStep>>>::None);
+    let last_recorded_at = store_value(0_f64);
+    let record_step = move |steps: RwSignal<Vec<MacroStep>>, event: KeyEvent| {
+        let now = Date::now();
+        steps.update(|steps| {
+            if steps.len() >= MAX_MACRO_STEPS {
+                return;
+            }
+            let delay_ms = if steps.is_empty() {
+                0
+            } else {
+                (now - last_recorded_at.get_value()).max(0.0) as u32
+            };
+            steps.push(MacroStep { delay_ms, event });
+        });
+        last_recorded_at.set_value(now);
+    };
     create_effect(move |_| {
         if let Some(key) = key_rx.get() {
-            if !is_editing_mode.get_untracked() {
+            if let Some(steps) = recording.get_untracked() {
+                record_step(steps, key);
+            } else if !is_editing_mode.get_untracked() {
                 keys_sender.set(Some(key));
             }
         }
     });
+    let _ = use_event_listener(window(), leptos::ev::keydown, move |ev| {
+        if let (Some(steps), Ok(key)) = (recording.get_untracked(), Key::try_from(ev.clone())) {
+            if !ev.repeat() {
+                record_step(steps, KeyEvent::Down(key, key_char(&ev)));
+            }
+        }
+    });
+    let _ = use_event_listener(window(), leptos::ev::keyup, move |ev| {
+        if let (Some(steps), Ok(key)) = (recording.get_untracked(), Key::try_from(ev.clone())) {
+            record_step(steps, KeyEvent::Up(key, key_char(&ev)));
+        }
+    });
     let (edit_position, set_editpostion) = create_signal((0_f32, 0_f32));
 
     let (is_edit_down, set_is_edit_down) = create_signal(false);
@@ -126,8 +173,18 @@ pub fn Gamepad(keys_sender: WriteSignal<Option<KeyEvent>>) -> impl IntoView {
                     class="absolute right-14 top-2 h-10 w-10 bg-blue-400 active:bg-blue-800 p-2 rounded-md pointer-events-auto"
                     title="Toggle Edit Mode"
                     on:click=move|_|{
+                        if is_editing_mode.get_untracked() {
+                            if let Some((game, orientation)) = edit_target.get_value() {
+                                layout_from_buttons(&gamebuttons.get_untracked())
+                                    .save(game.as_deref(), orientation);
+                            }
+                        } else {
+                            edit_target
+                                .set_value(Some((game.get_untracked(), orientation.get_untracked())));
+                        }
                         set_is_editing_mode.set(!is_editing_mode.get_untracked());
                         set_selected_button.set(None);
+                        recording.set(None);
                     }
                 >
                     <Icon icon=crate::components::icons::Icons::Edit />
@@ -226,6 +283,48 @@ pub fn Gamepad(keys_sender: WriteSignal<Option<KeyEvent>>) -> impl IntoView {
                                             />
                                         }.into_view()
                                     },
+                                    GamepadButtonType::Toggle(key) => {
+                                        view! {
+                                            <SingleButton key=key key_tx=key_tx
+                                                is_selected=is_selected
+                                                behavior=ButtonBehavior::Toggle
+                                            />
+                                        }.into_view()
+                                    },
+                                    GamepadButtonType::Turbo(key, rate) => {
+                                        view! {
+                                            <SingleButton key=key key_tx=key_tx
+                                                is_selected=is_selected
+                                                behavior=ButtonBehavior::Turbo(rate)
+                                            />
+                                        }.into_view()
+                                    },
+                                    GamepadButtonType::Macro(steps) => {
+                                        view! {
+                                            <MacroButton
+                                                key_tx=key_tx
+                                                steps=steps
+                                                is_selected=is_selected
+                                            />
+                                        }.into_view()
+                                    },
+                                    GamepadButtonType::Joystick(keys) => {
+                                        view! {
+                                            <VirtualJoystick
+                                                key_tx=key_tx
+                                                keys=keys
+                                                is_selected=is_selected
+                                            />
+                                        }.into_view()
+                                    },
+                                    GamepadButtonType::Trackpad => {
+                                        view! {
+                                            <Trackpad
+                                                key_tx=key_tx
+                                                is_selected=is_selected
+                                            />
+                                        }.into_view()
+                                    },
                                 }
                             }
                             </div>
@@ -283,6 +382,82 @@ pub fn Gamepad(keys_sender: WriteSignal<Option<KeyEvent>>) -> impl IntoView {
                     }
                 >
                     <div class="text-center text-sm"> "Edit Controls" </div>
+                    <div class="flex gap-1 text-xs flex-wrap">
+                        <button type="button" class="bg-blue-500 px-2 py-1 active:bg-blue-900"
+                            on:click=move|_|{
+                                match layout_from_buttons(&gamebuttons.get_untracked()).to_code() {
+                                    Ok(code) => {
+                                        let _ = window().prompt_with_message_and_default("Copy this layout code", &code);
+                                    }
+                                    Err(err) => warn!("Cant export layout {err:?}"),
+                                }
+                            }
+                        > "Export" </button>
+                        <button type="button" class="bg-blue-500 px-2 py-1 active:bg-blue-900"
+                            on:click=move|_|{
+                                if let Ok(Some(code)) = window().prompt_with_message("Paste a layout code") {
+                                    match GamepadLayout::from_code(&code) {
+                                        Ok(layout) => {
+                                            set_selected_button.set(None);
+                                            set_game_buttons.set(buttons_from_layout(&layout));
+                                        }
+                                        Err(err) => {
+                                            let _ = window().alert_with_message(&err.to_string());
+                                        }
+                                    }
+                                }
+                            }
+                        > "Import" </button>
+                        <button type="button" class="bg-blue-500 px-2 py-1 active:bg-blue-900"
+                            on:click=move|_|{
+                                set_selected_button.set(None);
+                                set_game_buttons.set(buttons_from_layout(&GamepadLayout::default()));
+                            }
+                        > "Reset" </button>
+                        <Show when={move || is_host.get()}>
+                            <button type="button" class="bg-blue-500 px-2 py-1 active:bg-blue-900"
+                                title="Recommend this layout to everyone in the room"
+                                on:click=move|_|{
+                                    match layout_from_buttons(&gamebuttons.get_untracked()).to_code() {
+                                        Ok(code) => expect_context::<RoomManager>().recommend_layout(code),
+                                        Err(err) => warn!("Cant export layout {err:?}"),
+                                    }
+                                }
+                            > "Share" </button>
+                        </Show>
+                        <Show when={move || !is_host.get() && recommended_layout.with(|l| l.is_some())}>
+                            <button type="button" class="bg-blue-500 px-2 py-1 active:bg-blue-900"
+                                on:click=move|_|{
+                                    if let Some(layout) = recommended_layout.get_untracked() {
+                                        set_selected_button.set(None);
+                                        set_game_buttons.set(buttons_from_layout(&layout));
+                                    }
+                                }
+                            > "Host Layout" </button>
+                        </Show>
+                    </div>
+                    <div class="flex gap-1 text-xs flex-wrap items-center">
+                        <span> "Add" </span>
+                        {
+                            [
+                                ("Key", ButtonKind::Button(Key::Z)),
+                                ("D-pad", ButtonKind::Dpad((Key::LeftArrow, Key::RightArrow, Key::UpArrow, Key::DownArrow))),
+                                ("Stick", ButtonKind::Joystick((Key::LeftArrow, Key::RightArrow, Key::UpArrow, Key::DownArrow))),
+                                ("Mouse", ButtonKind::Trackpad),
+                                ("Toggle", ButtonKind::Toggle(Key::CtrlLeft)),
+                                ("Turbo", ButtonKind::Turbo(Key::X, DEFAULT_TURBO_RATE)),
+                                ("Macro", ButtonKind::Macro(vec![])),
+                            ].into_iter().map(|(title, kind)| view! {
+                                <button type="button" class="bg-blue-500 px-2 py-1 active:bg-blue-900"
+                                    on:click=move|_|{
+                                        let button = button_from_layout(&ButtonLayout::new(kind.clone()));
+                                        set_selected_button.set(Some(button.id));
+                                        set_game_buttons.update(|buttons| buttons.push(button));
+                                    }
+                                > {title} </button>
+                            }).collect_view()
+                        }
+                    </div>
                     {
                         move || if let Some(selected_button) = selected_button.get().and_then(|button_id|
                             gamebuttons.get_untracked().into_iter().find(|b|b.id == button_id)
@@ -303,10 +478,16 @@ pub fn Gamepad(keys_sender: WriteSignal<Option<KeyEvent>>) -> impl IntoView {
                                         }
                                     > "+" </button>
                                 </div>
+                                <button type="button" class="bg-red-500 px-2 py-1 active:bg-red-900 text-xs"
+                                    on:click=move|_|{
+                                        set_game_buttons.update(|buttons| buttons.retain(|b| b.id != selected_button.id));
+                                        set_selected_button.set(None);
+                                    }
+                                > "Remove" </button>
 
                                 {
-                                    match selected_button.button {
-                                        GamepadButtonType::Dpad(keys) => {
+                                    match selected_button.button.clone() {
+                                        GamepadButtonType::Dpad(keys) | GamepadButtonType::Joystick(keys) => {
 
                                             let keys_info = vec![
                                                 ("Left Key", Signal::derive(move||keys.get().0), SignalSetter::map(move|k:Key|{
@@ -360,7 +541,10 @@ pub fn Gamepad(keys_sender: WriteSignal<Option<KeyEvent>>) -> impl IntoView {
                                                 }
                                             }.into_view()
                                         },
-                                        GamepadButtonType::Button(key) => {
+                                        GamepadButtonType::Trackpad | GamepadButtonType::Macro(_) => view! {}.into_view(),
+                                        GamepadButtonType::Button(key)
+                                        | GamepadButtonType::Toggle(key)
+                                        | GamepadButtonType::Turbo(key, _) => {
                                             view! {
                                                 <div class="flex gap-2 text-xs items-center">
 
@@ -390,6 +574,32 @@ pub fn Gamepad(keys_sender: WriteSignal<Option<KeyEvent>>) -> impl IntoView {
                                         },
                                     }
                                 }
+                                {
+                                    match selected_button.button {
+                                        GamepadButtonType::Turbo(_, rate) => view! {
+                                            <div class="flex gap-2 text-xs items-center">
+                                                <span> "Presses/s" </span>
+                                                <div class="flex-grow" />
+                                                <input
+                                                    class="bg-blue-500 px-2 py-1 w-16"
+                                                    type="number"
+                                                    min="1"
+                                                    max=MAX_TURBO_RATE
+                                                    prop:value=move||rate.get()
+                                                    on:change=move |ev| {
+                                                        if let Ok(value) = event_target_value(&ev).parse::<u32>() {
+                                                            rate.set(value.clamp(1, MAX_TURBO_RATE));
+                                                        }
+                                                    }
+                                                />
+                                            </div>
+                                        }.into_view(),
+                                        GamepadButtonType::Macro(steps) => view! {
+                                            <MacroEditor steps=steps recording=recording />
+                                        }.into_view(),
+                                        _ => view! {}.into_view(),
+                                    }
+                                }
                             }.into_view()
                         }else{
                             view! {}.into_view()
@@ -401,6 +611,56 @@ pub fn Gamepad(keys_sender: WriteSignal<Option<KeyEvent>>) -> impl IntoView {
     }
 }
 
+fn buttons_from_layout(layout: &GamepadLayout) -> Vec<GamepadButton> {
+    layout.buttons.iter().map(button_from_layout).collect()
+}
+
+fn button_from_layout(button: &ButtonLayout) -> GamepadButton {
+    GamepadButton {
+        id: uuid::Uuid::new_v4(),
+        position: RwSignal::new(button.position),
+        translation: RwSignal::new(button.translation),
+        width: RwSignal::new(button.width),
+        scale: RwSignal::new(button.scale),
+        button: match button.kind {
+            ButtonKind::Dpad(keys) => GamepadButtonType::Dpad(RwSignal::new(keys)),
+            ButtonKind::Button(key) => GamepadButtonType::Button(RwSignal::new(key)),
+            ButtonKind::Joystick(keys) => GamepadButtonType::Joystick(RwSignal::new(keys)),
+            ButtonKind::Trackpad => GamepadButtonType::Trackpad,
+            ButtonKind::Toggle(key) => GamepadButtonType::Toggle(RwSignal::new(key)),
+            ButtonKind::Turbo(key, rate) => {
+                GamepadButtonType::Turbo(RwSignal::new(key), RwSignal::new(rate))
+            }
+            ButtonKind::Macro(ref steps) => GamepadButtonType::Macro(RwSignal::new(steps.clone())),
+        },
+    }
+}
+
+fn layout_from_buttons(buttons: &[GamepadButton]) -> GamepadLayout {
+    GamepadLayout {
+        buttons: buttons
+            .iter()
+            .map(|button| ButtonLayout {
+                position: button.position.get_untracked(),
+                translation: button.translation.get_untracked(),
+                width: button.width.get_untracked(),
+                scale: button.scale.get_untracked(),
+                kind: match button.button {
+                    GamepadButtonType::Dpad(keys) => ButtonKind::Dpad(keys.get_untracked()),
+                    GamepadButtonType::Button(key) => ButtonKind::Button(key.get_untracked()),
+                    GamepadButtonType::Joystick(keys) => ButtonKind::Joystick(keys.get_untracked()),
+                    GamepadButtonType::Trackpad => ButtonKind::Trackpad,
+                    GamepadButtonType::Toggle(key) => ButtonKind::Toggle(key.get_untracked()),
+                    GamepadButtonType::Turbo(key, rate) => {
+                        ButtonKind::Turbo(key.get_untracked(), rate.get_untracked())
+                    }
+                    GamepadButtonType::Macro(steps) => ButtonKind::Macro(steps.get_untracked()),
+                },
+            })
+            .collect(),
+    }
+}
+
 #[component]
 fn GamepadDPad(
     key_tx: WriteSignal<Option<KeyEvent>>,
@@ -670,13 +930,13 @@ fn GamepadDPad(
                     SignalSetter::map(move |ev| match ev {
                         crate::components::touchmanager::TouchEvent::TouchEnter => {
                             for key in key.get_untracked().iter().cloned() {
-                                key_tx.set(Some(KeyEvent::Down(key)));
+                                key_tx.set(Some(KeyEvent::Down(key, key.char())));
                             }
                             active_set.set(true);
                         }
                         crate::components::touchmanager::TouchEvent::TouchLeave => {
                             for key in key.get_untracked().iter().cloned() {
-                                key_tx.set(Some(KeyEvent::Up(key)));
+                                key_tx.set(Some(KeyEvent::Up(key, key.char())));
                             }
                             active_set.set(false);
                         }
@@ -851,9 +1111,37 @@ fn SingleButton(
     key: RwSignal<Key>,
     key_tx: WriteSignal<Option<KeyEvent>>,
     is_selected: Memo<bool>,
+    #[prop(default = ButtonBehavior::Hold)] behavior: ButtonBehavior,
 ) -> impl IntoView {
     let div_ref = create_node_ref::<leptos::html::Div>();
     let (is_active, set_is_active) = create_signal(false);
+    // Whether a finger is on the button, touch enter repeats while it moves
+    let is_touched = store_value(false);
+    let turbo_handle = store_value(Option::<IntervalHandle>::None);
+    // Key the game sees held, released when the button goes away
+    let held = store_value(Option::<Key>::None);
+
+    let press = move |key: Key| {
+        held.set_value(Some(key));
+        key_tx.set(Some(KeyEvent::Down(key, key.char())));
+    };
+    let release = move |key: Key| {
+        held.set_value(None);
+        key_tx.set(Some(KeyEvent::Up(key, key.char())));
+    };
+    let stop_turbo = move || {
+        if let Some(handle) = turbo_handle.get_value() {
+            handle.clear();
+            turbo_handle.set_value(None);
+        }
+    };
+    on_cleanup(move || {
+        stop_turbo();
+        if let Some(key) = held.get_value() {
+            release(key);
+        }
+    });
+
     create_effect(move |_| {
         if let Some(div) = div_ref.get() {
             let touch_manager = expect_context::<TouchManager>();
@@ -867,12 +1155,61 @@ fn SingleButton(
                     },
                     SignalSetter::map(move |ev| match ev {
                         crate::components::touchmanager::TouchEvent::TouchEnter => {
-                            key_tx.set(Some(KeyEvent::Down(key.get_untracked())));
-                            set_is_active.set(true);
+                            let first_touch = !is_touched.get_value();
+                            is_touched.set_value(true);
+                            match behavior {
+                                ButtonBehavior::Hold => {
+                                    press(key.get_untracked());
+                                    set_is_active.set(true);
+                                }
+                                ButtonBehavior::Toggle if first_touch => {
+                                    if is_active.get_untracked() {
+                                        release(key.get_untracked());
+                                    } else {
+                                        press(key.get_untracked());
+                                    }
+                                    set_is_active.set(!is_active.get_untracked());
+                                }
+                                ButtonBehavior::Turbo(rate) if first_touch => {
+                                    press(key.get_untracked());
+                                    set_is_active.set(true);
+                                    let is_down = Cell::new(true);
+                                    let handle = set_interval_with_handle(
+                                        move || {
+                                            let key = key.get_untracked();
+                                            if is_down.get() {
+                                                release(key);
+                                            } else {
+                                                press(key);
+                                            }
+                                            is_down.set(!is_down.get());
+                                        },
+                                        Duration::from_secs_f64(
+                                            0.5 / f64::from(rate.get_untracked().max(1)),
+                                        ),
+                                    );
+                                    match handle {
+                                        Ok(handle) => turbo_handle.set_value(Some(handle)),
+                                        Err(err) => warn!("Cannot start turbo {err:?}"),
+                                    }
+                                }
+                                ButtonBehavior::Toggle | ButtonBehavior::Turbo(_) => {}
+                            }
                         }
                         crate::components::touchmanager::TouchEvent::TouchLeave => {
-                            key_tx.set(Some(KeyEvent::Up(key.get_untracked())));
-                            set_is_active.set(false);
+                            is_touched.set_value(false);
+                            match behavior {
+                                ButtonBehavior::Hold => {
+                                    release(key.get_untracked());
+                                    set_is_active.set(false);
+                                }
+                                ButtonBehavior::Toggle => {}
+                                ButtonBehavior::Turbo(_) => {
+                                    stop_turbo();
+                                    release(key.get_untracked());
+                                    set_is_active.set(false);
+                                }
+                            }
                         }
                     }),
                 );
@@ -902,6 +1239,104 @@ fn SingleButton(
                 {
                     move || key.get().get_symbol()
                 }
+                {
+                    match behavior {
+                        ButtonBehavior::Hold => None,
+                        ButtonBehavior::Toggle => Some("⇅"),
+                        ButtonBehavior::Turbo(_) => Some("»"),
+                    }.map(|marker| view! {
+                        <span class="absolute bottom-[15%] text-[0.6em] opacity-75"> {marker} </span>
+                    })
+                }
+            </div>
+
+            <div class="absolute w-full hexagon bg-blue-800 transition-all duration-200"
+                class=(["bg-yellow-500", "hexagon-selected"], is_selected)
+            />
+            <div class="w-[90%] h-[90%] hexagon bg-blue-800 absolute left-[5%] top-[5%]"
+                class=("hidden", move|| !is_selected.get())
+             />
+        </div>
+    }
+}
+
+#[component]
+fn MacroButton(
+    key_tx: WriteSignal<Option<KeyEvent>>,
+    steps: RwSignal<Vec<MacroStep>>,
+    is_selected: Memo<bool>,
+) -> impl IntoView {
+    let div_ref = create_node_ref::<leptos::html::Div>();
+    let (is_playing, set_is_playing) = create_signal(false);
+    let timeouts = store_value(Vec::<TimeoutHandle>::new());
+    // Presses the macro hasn't released yet, released if it's cut short
+    let held = store_value(Vec::<KeyEvent>::new());
+    let send = move |event: KeyEvent| {
+        held.update_value(|held| {
+            if event.release().is_some() {
+                held.push(event.clone());
+            } else {
+                held.retain(|press| !event.releases(press));
+            }
+        });
+        key_tx.set(Some(event));
+    };
+    on_cleanup(move || {
+        timeouts.update_value(|timeouts| timeouts.drain(..).for_each(TimeoutHandle::clear));
+        let presses = held.with_value(|held| held.clone());
+        held.set_value(Vec::new());
+        for release in presses.iter().filter_map(KeyEvent::release) {
+            key_tx.set(Some(release));
+        }
+    });
+
+    let play = move || {
+        let mut at = Duration::ZERO;
+        let mut handles = vec![];
+        for step in steps.get_untracked() {
+            at += Duration::from_millis(step.delay_ms.into());
+            match set_timeout_with_handle(move || send(step.event.clone()), at) {
+                Ok(handle) => handles.push(handle),
+                Err(err) => warn!("Cannot schedule macro step {err:?}"),
+            }
+        }
+        if let Ok(handle) = set_timeout_with_handle(move || set_is_playing.set(false), at) {
+            handles.push(handle);
+        }
+        set_is_playing.set(true);
+        timeouts.set_value(handles);
+    };
+
+    create_effect(move |_| {
+        if let Some(div) = div_ref.get() {
+            let touch_manager = expect_context::<TouchManager>();
+            touch_manager.register_listener(
+                {
+                    let el: &Element = div.as_ref();
+                    el.clone()
+                },
+                SignalSetter::map(move |ev| {
+                    if let crate::components::touchmanager::TouchEvent::TouchEnter = ev {
+                        if !is_playing.get_untracked() {
+                            play();
+                        }
+                    }
+                }),
+            );
+        }
+    });
+
+    view! {
+        <div class="w-full aspect-[cos(30deg)]">
+            <div
+                ref=div_ref
+                class="absolute hexagon-filled bg-blue-400/50 left-0 top-0 w-full h-full flex items-center justify-center"
+                class=("bg-blue-900", is_playing)
+            >
+                "M"
+                <span class="absolute bottom-[15%] text-[0.6em] opacity-75">
+                    {move || steps.with(Vec::len)}
+                </span>
             </div>
 
             <div class="absolute w-full hexagon bg-blue-800 transition-all duration-200"
@@ -913,3 +1348,271 @@ fn SingleButton(
         </div>
     }
 }
+
+/// Records and edits the steps of a macro button.
+#[component]
+fn MacroEditor(
+    steps: RwSignal<Vec<MacroStep>>,
+    recording: RwSignal<Option<RwSignal<Vec<MacroStep>>>>,
+) -> impl IntoView {
+    let is_recording = create_memo(move |_| recording.get() == Some(steps));
+    // Selecting another button stops the recording
+    on_cleanup(move || recording.set(None));
+
+    view! {
+        <div class="flex gap-2 text-xs items-center">
+            <button type="button" class="bg-blue-500 px-2 py-1 active:bg-blue-900"
+                class=("bg-red-500", is_recording)
+                on:click=move|_|{
+                    if is_recording.get_untracked() {
+                        recording.set(None);
+                    } else {
+                        steps.set(vec![]);
+                        recording.set(Some(steps));
+                    }
+                }
+            >
+                {move || if is_recording.get() { "Stop" } else { "Record" }}
+            </button>
+            <span> {move || if is_recording.get() { "Press keys or buttons" } else { "" }} </span>
+            <div class="flex-grow" />
+            <button type="button" class="bg-blue-500 px-2 py-1 active:bg-blue-900"
+                on:click=move|_|steps.set(vec![])
+            > "Clear" </button>
+        </div>
+        <div class="flex flex-col gap-1 text-xs max-h-32 overflow-auto">
+            {move || steps.get().into_iter().enumerate().map(|(index, step)| view! {
+                <div class="flex gap-2 items-center">
+                    <input
+                        class="bg-blue-500 px-1 w-16"
+                        type="number"
+                        min="0"
+                        prop:value=step.delay_ms
+                        on:change=move |ev| {
+                            if let Ok(value) = event_target_value(&ev).parse::<u32>() {
+                                steps.update(|steps| {
+                                    if let Some(step) = steps.get_mut(index) {
+                                        step.delay_ms = value;
+                                    }
+                                });
+                            }
+                        }
+                    />
+                    <span> "ms" </span>
+                    <span class="flex-grow"> {macro_step_label(&step.event)} </span>
+                    <button type="button" class="bg-blue-500 px-2 active:bg-blue-900"
+                        on:click=move|_|{
+                            steps.update(|steps| {
+                                if index < steps.len() {
+                                    steps.remove(index);
+                                }
+                            });
+                        }
+                    > "x" </button>
+                </div>
+            }).collect_view()}
+        </div>
+    }
+}
+
+fn macro_step_label(event: &KeyEvent) -> String {
+    match event {
+        KeyEvent::Down(key, _) => format!("{} down", key.get_symbol()),
+        KeyEvent::Up(key, _) => format!("{} up", key.get_symbol()),
+        KeyEvent::MouseMove(..) => "Mouse move".to_string(),
+        KeyEvent::MouseDown(_, _, button) => format!("{button:?} click down"),
+        KeyEvent::MouseUp(_, _, button) => format!("{button:?} click up"),
+        KeyEvent::MouseWheel(_) => "Mouse wheel".to_string(),
+        KeyEvent::MouseLeave => "Mouse leave".to_string(),
+        KeyEvent::MouseMoveBy(..) => "Mouse move".to_string(),
+        KeyEvent::CursorDown(button) => format!("{button:?} click down"),
+        KeyEvent::CursorUp(button) => format!("{button:?} click up"),
+    }
+}
+
+/// Fraction of the stick's radius the knob has to travel before a direction key is held.
+const JOYSTICK_THRESHOLD: f64 = 0.4;
+const JOYSTICK_DEAD_ZONE: f64 = 0.15;
+
+#[component]
+fn VirtualJoystick(
+    key_tx: WriteSignal<Option<KeyEvent>>,
+    keys: RwSignal<(Key, Key, Key, Key)>,
+    is_selected: Memo<bool>,
+) -> impl IntoView {
+    let div_ref = create_node_ref::<leptos::html::Div>();
+    let (knob, set_knob) = create_signal((0_f64, 0_f64));
+    let touch_id = store_value(Option::<i32>::None);
+    let held_keys = store_value(HashSet::<Key>::new());
+
+    let set_held = move |pressed: HashSet<Key>| {
+        let (released, newly_pressed) = held_keys.with_value(|held| {
+            (
+                held.difference(&pressed).copied().collect::<Vec<_>>(),
+                pressed.difference(held).copied().collect::<Vec<_>>(),
+            )
+        });
+        for key in released {
+            key_tx.set(Some(KeyEvent::Up(key, key.char())));
+        }
+        for key in newly_pressed {
+            key_tx.set(Some(KeyEvent::Down(key, key.char())));
+        }
+        held_keys.set_value(pressed);
+    };
+
+    let move_knob = move |touch: &web_sys::Touch| {
+        if let Some(div) = div_ref.get_untracked() {
+            let rect = div.get_bounding_client_rect();
+            let radius = rect.width() / 2.0;
+            let x = (f64::from(touch.client_x()) - rect.left() - radius) / radius;
+            let y = (f64::from(touch.client_y()) - rect.top() - radius) / radius;
+            // Keep the knob inside the base
+            let length = x.hypot(y).max(1.0);
+            let (x, y) = (x / length, y / length);
+            set_knob.set((x, y));
+            set_held(
+                stick_keys(
+                    (x, y),
+                    keys.get_untracked(),
+                    JOYSTICK_DEAD_ZONE,
+                    JOYSTICK_THRESHOLD,
+                )
+                .into_iter()
+                .collect(),
+            );
+        }
+    };
+
+    let find_touch = move |ev: &web_sys::TouchEvent| {
+        let touches = ev.changed_touches();
+        (0..touches.length())
+            .filter_map(|i| touches.get(i))
+            .find(|touch| Some(touch.identifier()) == touch_id.get_value())
+    };
+
+    view! {
+        <div class="w-full aspect-square relative touch-none">
+            <div
+                ref=div_ref
+                class="absolute rounded-full bg-blue-400/50 left-0 top-0 w-full h-full"
+                on:touchstart=move|ev|{
+                    if touch_id.get_value().is_none() {
+                        if let Some(touch) = ev.changed_touches().get(0) {
+                            touch_id.set_value(Some(touch.identifier()));
+                            move_knob(&touch);
+                        }
+                    }
+                }
+                on:touchmove=move|ev|{
+                    if let Some(touch) = find_touch(&ev) {
+                        ev.prevent_default();
+                        move_knob(&touch);
+                    }
+                }
+                on:touchend=move|ev|{
+                    if find_touch(&ev).is_some() {
+                        touch_id.set_value(None);
+                        set_knob.set((0.0, 0.0));
+                        set_held(HashSet::new());
+                    }
+                }
+                on:touchcancel=move|ev|{
+                    if find_touch(&ev).is_some() {
+                        touch_id.set_value(None);
+                        set_knob.set((0.0, 0.0));
+                        set_held(HashSet::new());
+                    }
+                }
+            >
+                <div
+                    class="absolute rounded-full bg-blue-900/85 w-[40%] h-[40%] pointer-events-none"
+                    style=move||{
+                        let (x, y) = knob.get();
+                        format!("left: {}%; top: {}%;", 30.0 + x * 30.0, 30.0 + y * 30.0)
+                    }
+                />
+            </div>
+            <div class="absolute w-full h-full rounded-full border-4 border-blue-800 pointer-events-none transition-all duration-200"
+                class=("border-yellow-500", is_selected)
+            />
+        </div>
+    }
+}
+
+/// How far a touch may wander and how long it may last and still count as a tap.
+const TAP_DISTANCE: f64 = 10.0;
+const TAP_DURATION_MS: f64 = 250.0;
+
+/// Moves the game's mouse by the finger's movement, laptop trackpad style.
+#[component]
+fn Trackpad(key_tx: WriteSignal<Option<KeyEvent>>, is_selected: Memo<bool>) -> impl IntoView {
+    let div_ref = create_node_ref::<leptos::html::Div>();
+    // Cursor position as a fraction of the game's size
+    let cursor = store_value((0.5_f64, 0.5_f64));
+    // Touch id, last position, start position and start time
+    let touch_state = store_value(Option::<(i32, (f64, f64), (f64, f64), f64)>::None);
+
+    let find_touch = move |ev: &web_sys::TouchEvent| {
+        let touches = ev.changed_touches();
+        (0..touches.length())
+            .filter_map(|i| touches.get(i))
+            .find(|touch| Some(touch.identifier()) == touch_state.get_value().map(|t| t.0))
+    };
+    let send_cursor = move |event: fn(f64, f64) -> KeyEvent| {
+        let (x, y) = cursor.get_value();
+        key_tx.set(Some(event(x, y)));
+    };
+
+    view! {
+        <div class="w-full aspect-[4/3] relative touch-none">
+            <div
+                ref=div_ref
+                class="absolute rounded-md bg-blue-400/50 left-0 top-0 w-full h-full flex items-center justify-center text-xs"
+                on:touchstart=move|ev|{
+                    if touch_state.get_value().is_none() {
+                        if let Some(touch) = ev.changed_touches().get(0) {
+                            let position = (f64::from(touch.client_x()), f64::from(touch.client_y()));
+                            touch_state.set_value(Some((touch.identifier(), position, position, Date::now())));
+                        }
+                    }
+                }
+                on:touchmove=move|ev|{
+                    if let (Some(touch), Some((id, last, start, started_at)), Some(div)) = (find_touch(&ev), touch_state.get_value(), div_ref.get_untracked()) {
+                        ev.prevent_default();
+                        let position = (f64::from(touch.client_x()), f64::from(touch.client_y()));
+                        // Crossing the pad moves the cursor across the whole game
+                        let rect = div.get_bounding_client_rect();
+                        let (x, y) = cursor.get_value();
+                        cursor.set_value((
+                            (x + (position.0 - last.0) / rect.width()).clamp(0.0, 1.0),
+                            (y + (position.1 - last.1) / rect.height()).clamp(0.0, 1.0),
+                        ));
+                        touch_state.set_value(Some((id, position, start, started_at)));
+                        send_cursor(KeyEvent::MouseMove);
+                    }
+                }
+                on:touchend=move|ev|{
+                    if let (Some(touch), Some((_, _, start, started_at))) = (find_touch(&ev), touch_state.get_value()) {
+                        touch_state.set_value(None);
+                        let distance = (f64::from(touch.client_x()) - start.0).hypot(f64::from(touch.client_y()) - start.1);
+                        if distance < TAP_DISTANCE && Date::now() - started_at < TAP_DURATION_MS {
+                            send_cursor(|x, y| KeyEvent::MouseDown(x, y, MouseButton::Left));
+                            send_cursor(|x, y| KeyEvent::MouseUp(x, y, MouseButton::Left));
+                        }
+                    }
+                }
+                on:touchcancel=move|ev|{
+                    if find_touch(&ev).is_some() {
+                        touch_state.set_value(None);
+                    }
+                }
+            >
+                "Mouse"
+            </div>
+            <div class="absolute w-full h-full rounded-md border-4 border-blue-800 pointer-events-none transition-all duration-200"
+                class=("border-yellow-500", is_selected)
+            />
+        </div>
+    }
+}
@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use leptos::*;
+use logging::warn;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::utils::invite::{invite_link, qr_path};
+
+/// How long the copy button says the link was copied.
+const COPIED_FEEDBACK: Duration = Duration::from_secs(2);
+
+/// Copy button and QR code of the invite link to `room_id`.
+#[component]
+pub fn Invite(room_id: String) -> impl IntoView {
+    let link = invite_link(&room_id);
+    let (copied, set_copied) = create_signal(false);
+    let show_qr = create_rw_signal(false);
+    let qr = qr_path(&link);
+
+    let copy = {
+        let link = link.clone();
+        move |_| {
+            let write = window().navigator().clipboard().write_text(&link);
+            spawn_local(async move {
+                match JsFuture::from(write).await {
+                    Ok(_) => {
+                        set_copied.set(true);
+                        set_timeout(move || set_copied.set(false), COPIED_FEEDBACK);
+                    }
+                    Err(err) => warn!("Cannot copy invite link {err:?}"),
+                }
+            });
+        }
+    };
+
+    view! {
+        <div class="w-full flex flex-col items-center">
+            <div class="flex gap-2">
+                <button class="text-xs hover:bg-white/20 px-2" on:click=copy>
+                    {move || if copied.get() { "[ Copied! ]" } else { "[ Copy invite link ]" }}
+                </button>
+                <button
+                    class="text-xs hover:bg-white/20 px-2"
+                    on:click=move |_| show_qr.update(|s| *s = !*s)
+                >
+                    "[ QR ]"
+                </button>
+            </div>
+            <Show when=move || show_qr.get()>
+                {match qr.clone() {
+                    Some((width, path)) => {
+                        // Leave the quiet zone scanners need around the code
+                        let view_box = format!("-4 -4 {} {}", width + 8, width + 8);
+                        view! {
+                            <svg
+                                class="w-40 h-40 mt-2 bg-white"
+                                viewBox=view_box
+                                shape-rendering="crispEdges"
+                            >
+                                <title>{link.clone()}</title>
+                                <path d=path fill="black" />
+                            </svg>
+                        }
+                            .into_view()
+                    }
+                    None => view! { <div class="text-xs text-red-400">"Link too long for a QR code"</div> }
+                        .into_view(),
+                }}
+            </Show>
+        </div>
+    }
+}
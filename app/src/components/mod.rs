@@ -1,7 +1,10 @@
 pub mod chatbox;
 pub mod dialog;
+pub mod game_cursor;
 pub mod gamepad;
 pub mod icons;
+pub mod invite;
+pub mod native_gamepad;
 pub mod player;
 #[cfg(all(
     feature = "ruffle_web_common",
@@ -10,7 +13,9 @@ pub mod player;
 ))]
 pub mod player_web;
 pub mod portal;
+pub mod profile;
 pub mod room_info;
+pub mod room_list;
 pub mod touchmanager;
 pub mod video_player;
 pub mod virtual_buttons;
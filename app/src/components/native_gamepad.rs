@@ -0,0 +1,373 @@
+use std::collections::HashSet;
+
+use codee::string::JsonSerdeCodec;
+use leptos::*;
+use leptos_use::{storage::use_local_storage, use_raf_fn};
+use strum::IntoEnumIterator;
+use wasm_bindgen::JsCast;
+
+use crate::{
+    components::dialog::Dialog,
+    utils::{
+        gamepad_mapping::{ButtonMapping, ControllerMapping, GamepadConfig},
+        keycode::{Key, KeyEvent},
+        touch_gestures::TouchGestureSettings,
+    },
+};
+
+const GAMEPAD_CONFIG_KEY: &str = "gamepad_config";
+
+/// Polls physical controllers every frame and sends the keys they map to.
+#[component]
+pub fn NativeGamepads(
+    keys_sender: WriteSignal<Option<KeyEvent>>,
+    settings_open: RwSignal<bool>,
+) -> impl IntoView {
+    let (config, set_config, _) =
+        use_local_storage::<GamepadConfig, JsonSerdeCodec>(GAMEPAD_CONFIG_KEY);
+    let (connected, set_connected) = create_signal(0_usize);
+    let held_keys = store_value(HashSet::<Key>::new());
+
+    use_raf_fn(move |_| {
+        let gamepads = connected_gamepads();
+        if gamepads.len() != connected.get_untracked() {
+            set_connected.set(gamepads.len());
+        }
+        if gamepads.is_empty() && held_keys.with_value(|keys| keys.is_empty()) {
+            return;
+        }
+
+        let pressed = config.with_untracked(|config| {
+            gamepads
+                .iter()
+                .zip(config.slots.iter())
+                .flat_map(|(gamepad, mapping)| {
+                    let buttons = gamepad
+                        .buttons()
+                        .iter()
+                        .map(|button| {
+                            button
+                                .dyn_into::<web_sys::GamepadButton>()
+                                .map(|button| button.pressed())
+                                .unwrap_or_default()
+                        })
+                        .collect::<Vec<_>>();
+                    let axes = gamepad
+                        .axes()
+                        .iter()
+                        .map(|axis| axis.as_f64().unwrap_or_default())
+                        .collect::<Vec<_>>();
+                    mapping.pressed_keys(&buttons, &axes)
+                })
+                .collect::<HashSet<_>>()
+        });
+
+        let (released, newly_pressed) = held_keys.with_value(|held| {
+            (
+                held.difference(&pressed).copied().collect::<Vec<_>>(),
+                pressed.difference(held).copied().collect::<Vec<_>>(),
+            )
+        });
+        for key in released {
+            keys_sender.set(Some(KeyEvent::Up(key, key.char())));
+        }
+        for key in newly_pressed {
+            keys_sender.set(Some(KeyEvent::Down(key, key.char())));
+        }
+        held_keys.set_value(pressed);
+    });
+
+    view! {
+        <Dialog
+            is_open=settings_open
+            on_close=move |_| {
+                settings_open.set(false);
+            }
+        >
+            <h3 class="font-bold2 text-xl text-center w-full">"Controllers"</h3>
+            <div class="text-xs text-center">
+                {move || format!("{} connected, press a button to wake one up", connected.get())}
+            </div>
+
+            <div class="h-4" />
+
+            <div class="max-h-[50vh] overflow-auto flex flex-col gap-4 text-xs">
+                {move || {
+                    (0..config.with(|c| c.slots.len()))
+                        .map(|slot| view! { <SlotSettings slot config set_config /> })
+                        .collect_view()
+                }}
+            </div>
+
+            <div class="h-4" />
+
+            <TouchGestureSettings />
+
+            <div class="h-4" />
+
+            <div class="flex gap-2 justify-center text-sm">
+                <button
+                    class="hover:bg-white/20 px-2"
+                    type="button"
+                    on:click=move |_| {
+                        set_config
+                            .update(|config| {
+                                config
+                                    .slots
+                                    .push(
+                                        ControllerMapping::with_dpad(
+                                            (Key::A, Key::D, Key::W, Key::S),
+                                            vec![],
+                                        ),
+                                    );
+                            });
+                    }
+                >
+                    "[ + Player ]"
+                </button>
+                <button
+                    class="hover:bg-white/20 px-2"
+                    type="button"
+                    on:click=move |_| {
+                        set_config.set(GamepadConfig::default());
+                    }
+                >
+                    "[ Reset ]"
+                </button>
+            </div>
+        </Dialog>
+    }
+}
+
+#[component]
+fn SlotSettings(
+    slot: usize,
+    config: Signal<GamepadConfig>,
+    set_config: WriteSignal<GamepadConfig>,
+) -> impl IntoView {
+    let update = move |f: &dyn Fn(&mut ControllerMapping)| {
+        set_config.update(|config| {
+            if let Some(mapping) = config.slots.get_mut(slot) {
+                f(mapping);
+            }
+        });
+    };
+    let mapping = move || config.with(|config| config.slots.get(slot).cloned());
+
+    view! {
+        <div class="border border-white/40 p-2 flex flex-col gap-1">
+            <div class="flex items-center gap-2">
+                <span class="font-bold2">{format!("Player {}", slot + 1)}</span>
+                <div class="flex-grow" />
+                <button
+                    class="hover:bg-white/20 px-1"
+                    type="button"
+                    on:click=move |_| {
+                        set_config
+                            .update(|config| {
+                                if slot < config.slots.len() {
+                                    config.slots.remove(slot);
+                                }
+                            });
+                    }
+                >
+                    "[ - ]"
+                </button>
+            </div>
+
+            <label class="flex items-center gap-2">
+                "Dead zone"
+                <div class="flex-grow" />
+                <input
+                    class="bg-white/10 w-16 p-1"
+                    type="number"
+                    min="0"
+                    max="1"
+                    step="0.05"
+                    prop:value=move || mapping().map(|m| m.dead_zone).unwrap_or_default()
+                    on:change=move |ev| {
+                        if let Ok(value) = event_target_value(&ev).parse::<f64>() {
+                            update(&|m| m.dead_zone = value.clamp(0.0, 1.0));
+                        }
+                    }
+                />
+            </label>
+            <label class="flex items-center gap-2">
+                "Stick threshold"
+                <div class="flex-grow" />
+                <input
+                    class="bg-white/10 w-16 p-1"
+                    type="number"
+                    min="0"
+                    max="1"
+                    step="0.05"
+                    prop:value=move || mapping().map(|m| m.threshold).unwrap_or_default()
+                    on:change=move |ev| {
+                        if let Ok(value) = event_target_value(&ev).parse::<f64>() {
+                            update(&|m| m.threshold = value.clamp(0.0, 1.0));
+                        }
+                    }
+                />
+            </label>
+
+            {move || {
+                mapping()
+                    .map(|mapping| {
+                        mapping
+                            .sticks
+                            .iter()
+                            .enumerate()
+                            .map(|(index, stick)| {
+                                let (left, right, up, down) = stick.keys;
+                                let set_key = move |direction: usize, key: Key| {
+                                    update(
+                                        &|m| {
+                                            if let Some(stick) = m.sticks.get_mut(index) {
+                                                match direction {
+                                                    0 => stick.keys.0 = key,
+                                                    1 => stick.keys.1 = key,
+                                                    2 => stick.keys.2 = key,
+                                                    _ => stick.keys.3 = key,
+                                                }
+                                            }
+                                        },
+                                    );
+                                };
+                                view! {
+                                    <div class="flex items-center gap-1 flex-wrap">
+                                        {format!("Axes {}/{}", stick.x_axis, stick.y_axis)}
+                                        <div class="flex-grow" />
+                                        <KeySelect key=left on_change=move |k| set_key(0, k) />
+                                        <KeySelect key=right on_change=move |k| set_key(1, k) />
+                                        <KeySelect key=up on_change=move |k| set_key(2, k) />
+                                        <KeySelect key=down on_change=move |k| set_key(3, k) />
+                                    </div>
+                                }
+                            })
+                            .collect_view()
+                    })
+            }}
+
+            {move || {
+                mapping()
+                    .map(|mapping| {
+                        mapping
+                            .buttons
+                            .iter()
+                            .enumerate()
+                            .map(|(index, button)| {
+                                view! {
+                                    <div class="flex items-center gap-1">
+                                        "Button"
+                                        <input
+                                            class="bg-white/10 w-12 p-1"
+                                            type="number"
+                                            min="0"
+                                            prop:value=button.button
+                                            on:change=move |ev| {
+                                                if let Ok(value) = event_target_value(&ev)
+                                                    .parse::<u32>()
+                                                {
+                                                    update(
+                                                        &|m| {
+                                                            if let Some(button) = m.buttons.get_mut(index) {
+                                                                button.button = value;
+                                                            }
+                                                        },
+                                                    );
+                                                }
+                                            }
+                                        />
+                                        <div class="flex-grow" />
+                                        <KeySelect
+                                            key=button.key
+                                            on_change=move |key| {
+                                                update(
+                                                    &|m| {
+                                                        if let Some(button) = m.buttons.get_mut(index) {
+                                                            button.key = key;
+                                                        }
+                                                    },
+                                                );
+                                            }
+                                        />
+                                        <button
+                                            class="hover:bg-white/20 px-1"
+                                            type="button"
+                                            on:click=move |_| {
+                                                update(
+                                                    &|m| {
+                                                        if index < m.buttons.len() {
+                                                            m.buttons.remove(index);
+                                                        }
+                                                    },
+                                                );
+                                            }
+                                        >
+                                            "[ - ]"
+                                        </button>
+                                    </div>
+                                }
+                            })
+                            .collect_view()
+                    })
+            }}
+
+            <button
+                class="hover:bg-white/20 px-1 self-start"
+                type="button"
+                on:click=move |_| {
+                    update(
+                        &|m| {
+                            let button = m.buttons.iter().map(|b| b.button + 1).max().unwrap_or_default();
+                            m.buttons
+                                .push(ButtonMapping {
+                                    button,
+                                    key: Key::Space,
+                                });
+                        },
+                    );
+                }
+            >
+                "[ + Button ]"
+            </button>
+        </div>
+    }
+}
+
+#[component]
+fn KeySelect(key: Key, #[prop(into)] on_change: Callback<Key>) -> impl IntoView {
+    view! {
+        <select
+            class="bg-blue-500 px-1 py-1 active:bg-blue-900"
+            on:change=move |ev| {
+                let new_value = event_target_value(&ev);
+                if let Some(k) = Key::iter().find(|k| k.get_symbol() == new_value) {
+                    on_change.call(k);
+                }
+            }
+        >
+            {Key::iter()
+                .map(|k| {
+                    view! {
+                        <option selected={k == key} value=k.get_symbol()>
+                            {k.get_symbol()}
+                        </option>
+                    }
+                })
+                .collect_view()}
+        </select>
+    }
+}
+
+/// Connected controllers ordered by their index.
+fn connected_gamepads() -> Vec<web_sys::Gamepad> {
+    match window().navigator().get_gamepads() {
+        Ok(gamepads) => gamepads
+            .iter()
+            .filter_map(|gamepad| gamepad.dyn_into::<web_sys::Gamepad>().ok())
+            .filter(|gamepad| gamepad.connected())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
@@ -1,27 +1,39 @@
-use std::future::IntoFuture;
+use std::{collections::HashMap, future::IntoFuture};
 
+use common::message::UserCursor;
 use ev::MessageEvent;
 use leptos::*;
-use leptos_use::{use_event_listener, use_event_listener_with_options, UseEventListenerOptions};
+use leptos_use::{use_event_listener, use_interval_fn, use_throttle_fn_with_arg};
 use logging::warn;
 use tracing::info;
+use uuid::Uuid;
 use wasm_bindgen::{JsCast, JsValue};
 use web_sys::{
-    js_sys::{Array, ArrayBuffer, Uint8Array},
-    Blob, DomRect, HtmlCanvasElement, HtmlElement, HtmlInputElement, MediaStreamTrack,
+    js_sys::{Array, ArrayBuffer, Date, Uint8Array},
+    Blob, Element, HtmlCanvasElement, HtmlElement, HtmlInputElement, MediaStreamTrack,
     RtcBundlePolicy, RtcConfiguration, RtcDataChannelInit, RtcIceConnectionState, RtcIceServer,
     RtcPeerConnection, RtcPeerConnectionState, RtcRtpTransceiverInit, RtcSessionDescription,
     RtcSessionDescriptionInit,
 };
 
 use crate::{
+    components::game_cursor::{is_pointer_locked, GameCursor, PointerLockButton, RemoteCursors},
     networking::{
         room_manager::{self, RoomManager},
         rtc_connect::receive_peer_connections,
     },
-    utils::keycode::{Key, KeyEvent},
+    utils::{
+        coordinates::{canvas_area, client_position, normalize, normalize_clamped, Rect},
+        keycode::{key_char, Key, KeyEvent, MouseButton, WheelDelta},
+        touch_gestures::use_touch_input,
+    },
 };
 
+/// How often the host's virtual cursor and players' cursors are shown to the room.
+const CURSOR_INTERVAL_MS: f64 = 50.0;
+/// Cursors of players who didn't move their mouse for this long are hidden.
+const CURSOR_TIMEOUT_MS: f64 = 3000.0;
+
 #[component]
 pub fn Player(
     swf_data: ReadSignal<Option<(String, Vec<u8>)>>,
@@ -39,91 +51,13 @@ pub fn Player(
         if let Some(canvas) = canvas_ref.get() {
             let el: &HtmlElement = canvas.as_ref();
             let el_html: HtmlElement = el.clone();
-            let _ = use_event_listener_with_options(
-                el_html.clone(),
-                leptos::ev::touchstart,
-                move |ev| {
-                    // info!("Recceived start");
-                    if let Some(canvas) = canvas_ref.get_untracked() {
-                        if let Some(touch) = ev.changed_touches().item(0) {
-                            let dpr = window().device_pixel_ratio();
-
-                            let rect = canvas.get_bounding_client_rect();
-                            let (x, y) = (f64::from(touch.client_x()), f64::from(touch.client_y()));
-
-                            if is_point_in_rect((x, y), rect.clone()) {
-                                let x = x - rect.left();
-                                let y = y - rect.top();
-                                key_event_tx.set(Some(KeyEvent::MouseDown(
-                                    x * dpr / rect.width(),
-                                    y * dpr / rect.height(),
-                                )));
-
-                                ev.prevent_default();
-                            }
-                        }
-                    }
-                },
-                UseEventListenerOptions::default().passive(false),
-            );
-
-            let _ = use_event_listener_with_options(
-                el_html.clone(),
-                leptos::ev::touchend,
-                move |ev| {
-                    // info!("Recceived end");
-                    if let Some(canvas) = canvas_ref.get_untracked() {
-                        if let Some(touch) = ev.changed_touches().item(0) {
-                            let dpr = window().device_pixel_ratio();
-
-                            let rect = canvas.get_bounding_client_rect();
-                            let (x, y) = (f64::from(touch.client_x()), f64::from(touch.client_y()));
-
-                            if is_point_in_rect((x, y), rect.clone()) {
-                                let x = x - rect.left();
-                                let y = y - rect.top();
-                                key_event_tx.set(Some(KeyEvent::MouseUp(
-                                    x * dpr / rect.width(),
-                                    y * dpr / rect.height(),
-                                )));
-
-                                ev.prevent_default();
-                            }
-                        }
-                    }
-                },
-                UseEventListenerOptions::default().passive(false),
-            );
-
-            let _ = use_event_listener_with_options(
-                el_html,
-                leptos::ev::touchmove,
-                move |ev| {
-                    if let Some(canvas) = canvas_ref.get_untracked() {
-                        if let Some(touch) = ev.changed_touches().item(0) {
-                            let dpr = window().device_pixel_ratio();
-
-                            let rect = canvas.get_bounding_client_rect();
-                            let (x, y) = (f64::from(touch.client_x()), f64::from(touch.client_y()));
-
-                            if is_point_in_rect((x, y), rect.clone()) {
-                                let x = x - rect.left();
-                                let y = y - rect.top();
-                                key_event_tx.set(Some(KeyEvent::MouseMove(
-                                    x * dpr / rect.width(),
-                                    y * dpr / rect.height(),
-                                )));
-
-                                ev.prevent_default();
-                            }
-                        }
-                    }
-                },
-                UseEventListenerOptions::default().passive(false),
-            );
+            use_touch_input(el_html, |el| canvas_area(el), key_event_tx);
         }
     });
 
+    // Where each player points, reported by their input and by our own mouse
+    let (pointer_rx, pointer_tx) = create_signal(None::<(Uuid, Option<(f64, f64)>)>);
+
     let owner = Owner::current();
     create_effect(move |_| {
         let room_manager = expect_context::<RoomManager>();
@@ -146,53 +80,185 @@ pub fn Player(
                     rtc_message_receiver,
                     rtc_tx,
                     key_event_tx,
+                    pointer_tx,
                 );
             });
         }
     });
+    // Virtual cursor moved by a locked pointer, drawn here and shown to the room
+    let game_cursor = create_rw_signal(None);
+    let room_manager = expect_context::<RoomManager>();
+    let room_info = room_manager.get_room_info();
+    let share_game_cursor = use_throttle_fn_with_arg(
+        {
+            let room_manager = room_manager.clone();
+            move |position| room_manager.share_game_cursor(position)
+        },
+        CURSOR_INTERVAL_MS,
+    );
+    create_effect(move |is_first_run: Option<bool>| {
+        let position = game_cursor.get();
+        if is_first_run.is_some() {
+            share_game_cursor(position);
+        }
+        false
+    });
+
+    let report_own_pointer = move |position: Option<(f64, f64)>| {
+        if let Some(user_id) = room_info.with_untracked(|r| r.as_ref().map(|r| r.user_id)) {
+            pointer_tx.set(Some((user_id, position)));
+        }
+    };
+
+    let cursors = create_rw_signal(Vec::<UserCursor>::new());
+    let last_moved = store_value(HashMap::<Uuid, f64>::new());
+    create_effect(move |_| {
+        if let Some((user_id, position)) = pointer_rx.get() {
+            cursors.update(|cursors| match position {
+                Some(position) => match cursors.iter_mut().find(|c| c.user_id == user_id) {
+                    Some(cursor) => cursor.position = position,
+                    None => cursors.push(UserCursor { user_id, position }),
+                },
+                None => cursors.retain(|c| c.user_id != user_id),
+            });
+            last_moved.update_value(|l| {
+                l.insert(user_id, Date::now());
+            });
+        }
+    });
+    let _ = use_interval_fn(
+        move || {
+            let now = Date::now();
+            let stale = last_moved.with_value(|l| {
+                l.iter()
+                    .filter(|(_, moved)| now - **moved > CURSOR_TIMEOUT_MS)
+                    .map(|(user_id, _)| *user_id)
+                    .collect::<Vec<_>>()
+            });
+            if !stale.is_empty() {
+                last_moved.update_value(|l| l.retain(|user_id, _| !stale.contains(user_id)));
+                cursors.update(|c| c.retain(|c| !stale.contains(&c.user_id)));
+            }
+        },
+        1000,
+    );
+    let share_cursors = use_throttle_fn_with_arg(
+        move |cursors| room_manager.share_cursors(cursors),
+        CURSOR_INTERVAL_MS,
+    );
+    create_effect(move |is_first_run: Option<bool>| {
+        let cursors = cursors.get();
+        if is_first_run.is_some() {
+            share_cursors(cursors);
+        }
+        false
+    });
+
+    let game_area = Signal::derive(move || {
+        let width = canvas_ref
+            .get()
+            .map(|c| c.client_width())
+            .unwrap_or_default();
+        let height = canvas_ref
+            .get()
+            .map(|c| c.client_height())
+            .unwrap_or_default();
+        Rect {
+            left: 0.0,
+            top: 0.0,
+            width: f64::from(width),
+            height: f64::from(height),
+        }
+    });
+
     view! {
+        <div class="h-full w-full relative"
+            class=("hidden", move || swf_data.with(|v| v.is_none()))
+        >
+        <div class="absolute right-1 top-1 z-10">
+            <PointerLockButton target=Signal::derive(move || {
+                canvas_ref.get().map(|canvas| {
+                    let el: &Element = canvas.as_ref();
+                    el.clone()
+                })
+            }) />
+        </div>
+        <GameCursor position=game_cursor area=game_area />
+        <RemoteCursors cursors area=game_area />
         <canvas ref=canvas_ref class="h-full w-full"
             tabindex="1"
-            class=("hidden", move || swf_data.with(|v| v.is_none()))
             on:mousemove=move|ev|{
                 if let Some(canvas) = canvas_ref.get_untracked(){
-                    let rect = canvas.get_bounding_client_rect();
-                    let dpr = window().device_pixel_ratio();
-                    key_event_tx.set(Some(KeyEvent::MouseMove(f64::from(ev.offset_x())*dpr/rect.width(), f64::from(ev.offset_y())*dpr/rect.height())));
+                    let area = canvas_area(&canvas);
+                    if is_pointer_locked(&canvas) {
+                        key_event_tx.set(Some(KeyEvent::MouseMoveBy(
+                            f64::from(ev.movement_x()) / area.width,
+                            f64::from(ev.movement_y()) / area.height,
+                        )));
+                    } else if let Some((x, y)) = normalize(client_position(&ev), area) {
+                        report_own_pointer(Some((x, y)));
+                        key_event_tx.set(Some(KeyEvent::MouseMove(x, y)));
+                    }
                 }
             }
             on:mousedown=move|ev|{
-                if let Some(canvas) = canvas_ref.get_untracked(){
-                    let rect = canvas.get_bounding_client_rect();
-                    let dpr = window().device_pixel_ratio();
-                    key_event_tx.set(Some(KeyEvent::MouseDown(f64::from(ev.offset_x())*dpr/rect.width(), f64::from(ev.offset_y())*dpr/rect.height())));
+                if let (Some(canvas), Some(button)) = (canvas_ref.get_untracked(), MouseButton::from_event(&ev)){
+                    if is_pointer_locked(&canvas) {
+                        key_event_tx.set(Some(KeyEvent::CursorDown(button)));
+                    } else if let Some((x, y)) = normalize(client_position(&ev), canvas_area(&canvas)) {
+                        key_event_tx.set(Some(KeyEvent::MouseDown(x, y, button)));
+                    }
                 }
             }
             on:mouseup=move|ev|{
-                if let Some(canvas) = canvas_ref.get_untracked(){
-                    let rect = canvas.get_bounding_client_rect();
-                    let dpr = window().device_pixel_ratio();
-                    key_event_tx.set(Some(KeyEvent::MouseUp(f64::from(ev.offset_x())*dpr/rect.width(), f64::from(ev.offset_y())*dpr/rect.height())));
+                if let (Some(canvas), Some(button)) = (canvas_ref.get_untracked(), MouseButton::from_event(&ev)){
+                    if is_pointer_locked(&canvas) {
+                        key_event_tx.set(Some(KeyEvent::CursorUp(button)));
+                    } else {
+                        let (x, y) = normalize_clamped(client_position(&ev), canvas_area(&canvas));
+                        key_event_tx.set(Some(KeyEvent::MouseUp(x, y, button)));
+                    }
+                }
+            }
+            on:wheel=move|ev|{
+                if let Some(delta) = WheelDelta::from_event(&ev){
+                    ev.prevent_default();
+                    key_event_tx.set(Some(KeyEvent::MouseWheel(delta)));
                 }
             }
+            on:mouseleave=move|_|{
+                report_own_pointer(None);
+                key_event_tx.set(Some(KeyEvent::MouseLeave));
+            }
+            on:contextmenu=move|ev|{
+                // Right clicks belong to the game
+                ev.prevent_default();
+            }
 
             on:keydown=move|ev|{
-                if let Ok(key) = Key::try_from(ev){
-                    key_event_tx.set(Some(KeyEvent::Down(key)));
+                let key_char = key_char(&ev);
+                if let Ok(key) = Key::try_from(ev.clone()){
+                    if key == Key::Tab {
+                        // Keep focus on the game
+                        ev.prevent_default();
+                    }
+                    key_event_tx.set(Some(KeyEvent::Down(key, key_char)));
                 }else {
                     warn!("Cant convert event to KeyEvent")
                 }
             }
 
             on:keyup=move|ev|{
-                if let Ok(key) = Key::try_from(ev){
-                    key_event_tx.set(Some(KeyEvent::Up(key)));
+                let key_char = key_char(&ev);
+                if let Ok(key) = Key::try_from(ev.clone()){
+                    key_event_tx.set(Some(KeyEvent::Up(key, key_char)));
                 }else{
                     warn!("Cant convert event to KeyEvent")
                 }
             }
 
         ></canvas>
+        </div>
         {
             move || {
                 if is_web.get() {
@@ -209,6 +275,7 @@ pub fn Player(
                                 canvas_ref=canvas_ref
                                 swf_data=swf_data
                                 key_event_rx
+                                game_cursor=game_cursor.write_only()
                             />
                         }.into_view()
                     }
@@ -224,10 +291,3 @@ pub fn Player(
         }
     }
 }
-
-pub fn is_point_in_rect(point: (f64, f64), rect: DomRect) -> bool {
-    point.0 > rect.left()
-        && point.0 < rect.right()
-        && point.1 > rect.top()
-        && point.1 < rect.bottom()
-}
@@ -26,14 +26,20 @@ use base64::Engine;
 use ruffle_core::backend::storage::StorageBackend;
 use web_sys::Storage;
 
-use crate::utils::keycode::{Key, KeyEvent};
+use crate::utils::{
+    coordinates::{stage_area, to_viewport, Size, VirtualCursor},
+    keycode::{Key, KeyEvent, MouseButton, WheelDelta},
+};
 
 #[component]
 pub fn PlayerWeb(
     canvas_ref: NodeRef<leptos::html::Canvas>,
     swf_data: ReadSignal<Option<(String, Vec<u8>)>>,
     key_event_rx: ReadSignal<Option<KeyEvent>>,
+    /// Virtual cursor position while it is moved by a locked pointer
+    game_cursor: WriteSignal<Option<(f64, f64)>>,
 ) -> impl IntoView {
+    let cursor = store_value(VirtualCursor::default());
     let (player, set_player) = create_signal(Option::<Arc<Mutex<ruffle_core::Player>>>::None);
     let (timestamp, set_timestamp) = create_signal(None);
     let (canvas_data, set_canvas_data) = create_signal((0, 0, window().device_pixel_ratio()));
@@ -60,11 +66,31 @@ pub fn PlayerWeb(
             if let Some(player) = player.get() {
                 if let Some(event) = key_event_rx.get() {
                     if let Ok(player) = &mut player.lock() {
-                        let ruffleevent = event.ruffle_event(canvas);
-                        // info!("Sending event {ruffleevent:?}");
-                        // info!("Is mouse in stage {}", player.mouse_in_stage());
-                        let is_handled = player.handle_event(ruffleevent);
-                        // info!("Is handled {is_handled}")
+                        let rect = canvas.get_bounding_client_rect();
+                        let stage = stage_area(
+                            Size {
+                                width: rect.width(),
+                                height: rect.height(),
+                            },
+                            Size {
+                                width: f64::from(player.movie_width()),
+                                height: f64::from(player.movie_height()),
+                            },
+                        );
+                        let mut virtual_cursor = cursor.get_value();
+                        let event = virtual_cursor.resolve(event, stage);
+                        let position = |c: VirtualCursor| c.is_relative.then_some(c.position);
+                        if position(virtual_cursor) != position(cursor.get_value()) {
+                            game_cursor.set(position(virtual_cursor));
+                        }
+                        cursor.set_value(virtual_cursor);
+
+                        for ruffleevent in event.ruffle_events(canvas) {
+                            // info!("Sending event {ruffleevent:?}");
+                            // info!("Is mouse in stage {}", player.mouse_in_stage());
+                            let is_handled = player.handle_event(ruffleevent);
+                            // info!("Is handled {is_handled}")
+                        }
                     }
                 }
             }
@@ -374,41 +400,162 @@ impl Key {
             Key::X => KeyCode::X,
             Key::Y => KeyCode::Y,
             Key::Z => KeyCode::Z,
+
+            Key::Digit0 => KeyCode::KEY0,
+            Key::Digit1 => KeyCode::KEY1,
+            Key::Digit2 => KeyCode::KEY2,
+            Key::Digit3 => KeyCode::KEY3,
+            Key::Digit4 => KeyCode::KEY4,
+            Key::Digit5 => KeyCode::KEY5,
+            Key::Digit6 => KeyCode::KEY6,
+            Key::Digit7 => KeyCode::KEY7,
+            Key::Digit8 => KeyCode::KEY8,
+            Key::Digit9 => KeyCode::KEY9,
+
+            Key::ShiftLeft => KeyCode::SHIFT,
+            Key::ShiftRight => KeyCode::SHIFT,
+            Key::AltLeft => KeyCode::ALT,
+            Key::AltRight => KeyCode::ALT,
+            Key::MetaLeft => KeyCode::COMMAND,
+            Key::MetaRight => KeyCode::COMMAND,
+
+            Key::Tab => KeyCode::TAB,
+            Key::Escape => KeyCode::ESCAPE,
+            Key::Backspace => KeyCode::BACKSPACE,
+            Key::CapsLock => KeyCode::CAPS_LOCK,
+            Key::Insert => KeyCode::INSERT,
+            Key::Delete => KeyCode::DELETE,
+            Key::Home => KeyCode::HOME,
+            Key::End => KeyCode::END,
+            Key::PageUp => KeyCode::PG_UP,
+            Key::PageDown => KeyCode::PG_DOWN,
+            Key::Pause => KeyCode::PAUSE,
+            Key::NumLock => KeyCode::NUM_LOCK,
+            Key::ScrollLock => KeyCode::SCROLL_LOCK,
+
+            Key::F1 => KeyCode::F1,
+            Key::F2 => KeyCode::F2,
+            Key::F3 => KeyCode::F3,
+            Key::F4 => KeyCode::F4,
+            Key::F5 => KeyCode::F5,
+            Key::F6 => KeyCode::F6,
+            Key::F7 => KeyCode::F7,
+            Key::F8 => KeyCode::F8,
+            Key::F9 => KeyCode::F9,
+            Key::F10 => KeyCode::F10,
+            Key::F11 => KeyCode::F11,
+            Key::F12 => KeyCode::F12,
+
+            Key::Semicolon => KeyCode::SEMICOLON,
+            Key::Equal => KeyCode::EQUALS,
+            Key::Comma => KeyCode::COMMA,
+            Key::Minus => KeyCode::MINUS,
+            Key::Period => KeyCode::PERIOD,
+            Key::Slash => KeyCode::SLASH,
+            Key::Backquote => KeyCode::GRAVE,
+            Key::BracketLeft => KeyCode::LBRACKET,
+            Key::Backslash => KeyCode::BACKSLASH,
+            Key::BracketRight => KeyCode::RBRACKET,
+            Key::Quote => KeyCode::APOSTROPHE,
+
+            Key::Numpad0 => KeyCode::NUMPAD0,
+            Key::Numpad1 => KeyCode::NUMPAD1,
+            Key::Numpad2 => KeyCode::NUMPAD2,
+            Key::Numpad3 => KeyCode::NUMPAD3,
+            Key::Numpad4 => KeyCode::NUMPAD4,
+            Key::Numpad5 => KeyCode::NUMPAD5,
+            Key::Numpad6 => KeyCode::NUMPAD6,
+            Key::Numpad7 => KeyCode::NUMPAD7,
+            Key::Numpad8 => KeyCode::NUMPAD8,
+            Key::Numpad9 => KeyCode::NUMPAD9,
+            Key::NumpadMultiply => KeyCode::MULTIPLY,
+            Key::NumpadAdd => KeyCode::PLUS,
+            Key::NumpadSubtract => KeyCode::NUMPAD_MINUS,
+            Key::NumpadDecimal => KeyCode::NUMPAD_PERIOD,
+            Key::NumpadDivide => KeyCode::NUMPAD_SLASH,
+            Key::NumpadEnter => KeyCode::RETURN,
+        }
+    }
+}
+
+impl MouseButton {
+    pub fn ruffle_button(&self) -> ruffle_core::events::MouseButton {
+        match self {
+            MouseButton::Left => ruffle_core::events::MouseButton::Left,
+            MouseButton::Middle => ruffle_core::events::MouseButton::Middle,
+            MouseButton::Right => ruffle_core::events::MouseButton::Right,
         }
     }
 }
 
 impl KeyEvent {
-    pub fn ruffle_event(
+    /// Relative input has no ruffle equivalent, resolve it with a
+    /// [`VirtualCursor`] first.
+    pub fn ruffle_events(
         &self,
         canvas: leptos::HtmlElement<leptos::html::Canvas>,
-    ) -> ruffle_core::events::PlayerEvent {
+    ) -> Vec<ruffle_core::events::PlayerEvent> {
         let rect = canvas.get_bounding_client_rect();
-        match self {
-            KeyEvent::Down(key) => ruffle_core::PlayerEvent::KeyDown {
-                key_code: key.ruffle_key(),
-                key_char: None,
-            },
-            KeyEvent::Up(key) => ruffle_core::PlayerEvent::KeyUp {
+        let viewport = Size {
+            width: rect.width(),
+            height: rect.height(),
+        };
+        let to_viewport =
+            |x: f64, y: f64| to_viewport((x, y), viewport, window().device_pixel_ratio());
+        let event = match self {
+            KeyEvent::Down(key, key_char) => {
+                let key_down = ruffle_core::PlayerEvent::KeyDown {
+                    key_code: key.ruffle_key(),
+                    key_char: *key_char,
+                };
+                // Text fields are filled from TextInput, sent right after the
+                // key press like ruffle's own web frontend does
+                return match key_char.filter(|c| !c.is_control()) {
+                    Some(codepoint) => {
+                        vec![key_down, ruffle_core::PlayerEvent::TextInput { codepoint }]
+                    }
+                    None => vec![key_down],
+                };
+            }
+            KeyEvent::Up(key, key_char) => ruffle_core::PlayerEvent::KeyUp {
                 key_code: key.ruffle_key(),
-                key_char: None,
-            },
-            KeyEvent::MouseMove(x, y) => ruffle_core::PlayerEvent::MouseMove {
-                x: (*x) * rect.width(),
-                y: (*y) * rect.height(),
+                key_char: *key_char,
             },
-            KeyEvent::MouseDown(x, y) => ruffle_core::PlayerEvent::MouseDown {
-                x: (*x) * rect.width(),
-                y: (*y) * rect.height(),
-                button: ruffle_core::events::MouseButton::Left,
-                index: None,
-            },
-            KeyEvent::MouseUp(x, y) => ruffle_core::PlayerEvent::MouseUp {
-                x: (*x) * rect.width(),
-                y: (*y) * rect.height(),
-                button: ruffle_core::events::MouseButton::Left,
+            KeyEvent::MouseMove(x, y) => {
+                let (x, y) = to_viewport(*x, *y);
+                ruffle_core::PlayerEvent::MouseMove { x, y }
+            }
+            KeyEvent::MouseDown(x, y, button) => {
+                let (x, y) = to_viewport(*x, *y);
+                ruffle_core::PlayerEvent::MouseDown {
+                    x,
+                    y,
+                    button: button.ruffle_button(),
+                    index: None,
+                }
+            }
+            KeyEvent::MouseUp(x, y, button) => {
+                let (x, y) = to_viewport(*x, *y);
+                ruffle_core::PlayerEvent::MouseUp {
+                    x,
+                    y,
+                    button: button.ruffle_button(),
+                }
+            }
+            KeyEvent::MouseWheel(delta) => ruffle_core::PlayerEvent::MouseWheel {
+                delta: match delta {
+                    WheelDelta::Lines(lines) => ruffle_core::events::MouseWheelDelta::Lines(*lines),
+                    WheelDelta::Pixels(pixels) => {
+                        ruffle_core::events::MouseWheelDelta::Pixels(*pixels)
+                    }
+                },
             },
-        }
+            KeyEvent::MouseLeave => ruffle_core::PlayerEvent::MouseLeave,
+            KeyEvent::MouseMoveBy(..) | KeyEvent::CursorDown(_) | KeyEvent::CursorUp(_) => {
+                return Vec::new()
+            }
+        };
+        vec![event]
     }
 }
 
@@ -0,0 +1,53 @@
+use common::{Profile, MAX_NAME_LEN};
+use leptos::*;
+
+/// Name, color and avatar inputs editing `profile`.
+#[component]
+pub fn ProfileFields(profile: RwSignal<Profile>) -> impl IntoView {
+    view! {
+        <div class="flex items-center">
+            <label class=" font-thin8 text-sm" for="name">
+                "Name: "
+            </label>
+            <input
+                class="bg-white/10 focus:outline-white/50  text-md font-thin8 p-2"
+                name="name"
+                type="text"
+                placeholder="Enter your name"
+                maxlength=MAX_NAME_LEN
+                prop:value=move || profile.with(|p| p.name.clone())
+                on:input=move |ev| {
+                    profile.update(|p| p.name = event_target_value(&ev));
+                }
+            />
+        </div>
+
+        <div class="flex items-center gap-2 mt-2">
+            <label class=" font-thin8 text-sm" for="color">
+                "Color: "
+            </label>
+            <input
+                class="bg-transparent h-8 w-12"
+                name="color"
+                type="color"
+                prop:value=move || profile.with(|p| p.color.clone().unwrap_or("#ffffff".into()))
+                on:input=move |ev| {
+                    profile.update(|p| p.color = Some(event_target_value(&ev)));
+                }
+            />
+            <label class=" font-thin8 text-sm" for="emoji">
+                "Avatar: "
+            </label>
+            <input
+                class="bg-white/10 focus:outline-white/50 text-md p-2 w-16"
+                name="emoji"
+                type="text"
+                placeholder="🙂"
+                prop:value=move || profile.with(|p| p.emoji.clone().unwrap_or_default())
+                on:input=move |ev| {
+                    profile.update(|p| p.emoji = Some(event_target_value(&ev)));
+                }
+            />
+        </div>
+    }
+}
@@ -1,13 +1,37 @@
+use common::Profile;
 use leptos::*;
 
+use crate::components::invite::Invite;
 use crate::components::portal::Portal;
+use crate::components::profile::ProfileFields;
 use crate::networking::room_manager::RoomManager;
+use crate::utils::profile::{display_name, use_stored_profile, user_color};
 use crate::MountPoints;
 
 #[component]
 pub fn RoomInfo() -> impl IntoView {
     let room_manager = expect_context::<RoomManager>();
     let room_info = room_manager.get_room_info();
+    let send_metrics = room_manager.get_send_metrics();
+    let own_profile = move || {
+        room_info.with(|r| {
+            r.as_ref()
+                .and_then(|r| r.users.iter().find(|u| u.id == r.user_id))
+                .map(|u| u.profile())
+        })
+    };
+    // Remember the profile the server accepted for the next room
+    let (_, set_stored_profile) = use_stored_profile();
+    create_effect(move |_| {
+        if let Some(profile) = own_profile() {
+            set_stored_profile.set(profile);
+        }
+    });
+    // Keeps the invite from being redrawn on every room update
+    let room_id = create_memo(move |_| room_info.with(|r| r.as_ref().map(|r| r.id.clone())));
+    let editing = create_rw_signal(false);
+    let edited_profile = create_rw_signal(Profile::default());
+    let (profile_error, set_profile_error) = create_signal(None::<String>);
     view! {
         {move || {
             let mount_points = expect_context::<MountPoints>();
@@ -29,8 +53,44 @@ pub fn RoomInfo() -> impl IntoView {
                                 None => "Disconnected".to_string(),
                             }}
                         </div>
+                        {move || room_id.get().map(|room_id| view! { <Invite room_id /> })}
                         <hr class="border-white border-t w-full" />
 
+                        <button
+                            class="text-xs hover:bg-white/20 px-2 self-end"
+                            on:click=move |_| {
+                                if let Some(profile) = own_profile() {
+                                    edited_profile.set(profile);
+                                }
+                                set_profile_error.set(None);
+                                editing.update(|e| *e = !*e);
+                            }
+                        >
+                            {move || if editing.get() { "[ Cancel ]" } else { "[ Edit profile ]" }}
+                        </button>
+                        <Show when=move || editing.get()>
+                            <div class="w-full flex flex-col">
+                                <ProfileFields profile=edited_profile />
+                                <div class="text-xs text-red-400">{profile_error}</div>
+                                <button
+                                    class="text-sm hover:bg-white/20 self-center px-4 py-1"
+                                    on:click=move |_| {
+                                        let profile = edited_profile.get_untracked().normalized();
+                                        match profile.validate() {
+                                            Ok(()) => {
+                                                expect_context::<RoomManager>()
+                                                    .update_profile(profile);
+                                                editing.set(false);
+                                            }
+                                            Err(err) => set_profile_error.set(Some(err.to_string())),
+                                        }
+                                    }
+                                >
+                                    "[ Save ]"
+                                </button>
+                            </div>
+                        </Show>
+
                         {move || {
                             room_info
                                 .with(|r| r.as_ref().map(|r| r.users.clone()))
@@ -39,16 +99,46 @@ pub fn RoomInfo() -> impl IntoView {
                                 .map(|user| {
                                     view! {
                                         <div class="text-left w-full mt-2 break-words">
-                                            "> " {user.name}
+                                            "> "
+                                            <span style:color=user_color(&user)>
+                                                {display_name(&user)}
+                                            </span>
+                                            {user.slot.map(|slot| format!(" P{slot}"))}
+                                            {user
+                                                .role
+                                                .is_spectator()
+                                                .then_some(" (spectating)")}
                                             {match user.state {
                                                 common::UserState::VideoNotSelected => "⌛",
                                                 common::UserState::VideoSelected(_) => "✔️",
                                             }}
+                                            {user
+                                                .latency
+                                                .map(|latency| {
+                                                    view! {
+                                                        <span class="text-xs text-white/60">
+                                                            " " {latency} "ms"
+                                                        </span>
+                                                    }
+                                                })}
                                         </div>
                                     }
                                 })
                                 .collect::<Vec<_>>()
                         }}
+
+                        <div class="text-xs text-white/60 text-left w-full mt-auto pt-2">
+                            {move || {
+                                let metrics = send_metrics.get();
+                                format!(
+                                    "Sent {} · queued {} · dropped {} · coalesced {}",
+                                    metrics.sent,
+                                    metrics.queued,
+                                    metrics.dropped,
+                                    metrics.coalesced,
+                                )
+                            }}
+                        </div>
                     </Portal>
                 }
                     .into_view()
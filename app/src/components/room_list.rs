@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use leptos::*;
+use logging::warn;
+
+use crate::networking::room_list::fetch_public_rooms;
+
+/// How often the list is fetched again while it's shown.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(15);
+
+fn format_uptime(secs: u64) -> String {
+    match secs / 60 {
+        0 => "just now".to_string(),
+        minutes @ 1..=59 => format!("{minutes}m"),
+        minutes => format!("{}h {}m", minutes / 60, minutes % 60),
+    }
+}
+
+/// Public rooms, `on_join` gets the id of the room picked.
+#[component]
+pub fn RoomList(#[prop(into)] on_join: Callback<String>) -> impl IntoView {
+    let (refresh, set_refresh) = create_signal(0_u32);
+    let rooms = create_local_resource(move || refresh.get(), |_| fetch_public_rooms());
+    create_effect(move |_| {
+        match set_interval_with_handle(move || set_refresh.update(|r| *r += 1), REFRESH_INTERVAL) {
+            Ok(handle) => on_cleanup(move || handle.clear()),
+            Err(err) => warn!("Cannot refresh room list {err:?}"),
+        }
+    });
+
+    view! {
+        <div class="w-full max-w-xl flex flex-col">
+            <div class="flex items-center justify-between">
+                <h2 class="font-bold2 text-lg">"Public rooms"</h2>
+                <button class="text-sm hover:bg-white/20 px-2" on:click=move |_| rooms.refetch()>
+                    "[ Refresh ]"
+                </button>
+            </div>
+            <div class="h-2" />
+            {move || match rooms.get() {
+                None => view! { <div class="text-sm text-white/60">"Loading..."</div> }.into_view(),
+                Some(Err(err)) => {
+                    warn!("Cannot load room list {err:?}");
+                    view! { <div class="text-sm text-red-400">"Couldn't load the room list"</div> }
+                        .into_view()
+                }
+                Some(Ok(rooms)) if rooms.is_empty() => {
+                    view! { <div class="text-sm text-white/60">"No public rooms right now"</div> }
+                        .into_view()
+                }
+                Some(Ok(rooms)) => {
+                    rooms
+                        .into_iter()
+                        .map(|room| {
+                            let room_id = room.room_id.clone();
+                            let is_full = room.is_full();
+                            view! {
+                                <div class="flex items-center gap-2 border-b border-white/20 py-2">
+                                    <div class="flex flex-col flex-1 min-w-0">
+                                        <div class="font-thin8 text-md break-words">{room.title}</div>
+                                        <div class="text-xs text-white/60 break-words">
+                                            {room.game.unwrap_or_else(|| "No game picked yet".into())}
+                                        </div>
+                                    </div>
+                                    <div class="text-xs text-white/60 text-right">
+                                        <div>
+                                            {room.players} "/" {room.limits.max_players} " players"
+                                        </div>
+                                        {(room.spectators != 0)
+                                            .then(|| view! { <div>{room.spectators} " spectating"</div> })}
+                                        <div>{format_uptime(room.uptime_secs)}</div>
+                                    </div>
+                                    <button
+                                        class="text-sm hover:bg-white/20 px-2 py-1 disabled:text-white/40"
+                                        disabled=is_full
+                                        on:click=move |_| on_join.call(room_id.clone())
+                                    >
+                                        {if is_full { "[ Full ]" } else { "[ Join ]" }}
+                                    </button>
+                                </div>
+                            }
+                        })
+                        .collect_view()
+                }
+            }}
+        </div>
+    }
+}
@@ -1,12 +1,19 @@
 use leptos::*;
-use leptos_use::{use_event_listener_with_options, UseEventListenerOptions};
 use tracing::{info, warn};
-use web_sys::MediaStream;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, MediaStream};
 
 use crate::{
-    components::player::is_point_in_rect,
-    networking::{room_manager::RoomManager, rtc_connect::connect_to_host},
-    utils::keycode::{Key, KeyEvent},
+    components::game_cursor::{is_pointer_locked, GameCursor, PointerLockButton, RemoteCursors},
+    networking::{
+        room_manager::RoomManager,
+        rtc_connect::{connect_to_host, InputTransport},
+    },
+    utils::{
+        coordinates::{client_position, normalize, normalize_clamped, video_area, Rect},
+        keycode::{key_char, Key, KeyEvent, MouseButton, WheelDelta},
+        touch_gestures::use_touch_input,
+    },
 };
 
 #[component]
@@ -17,6 +24,7 @@ pub fn VideoPlayer(
     let video_node = create_node_ref::<leptos::html::Video>();
 
     let (media_stream, set_media_stream) = create_signal(Option::<MediaStream>::None);
+    let (input_transport, set_input_transport) = create_signal(InputTransport::WebSocket);
 
     create_effect(move |_| {
         if let (Some(media_stream), Some(video)) = (media_stream.get(), video_node.get()) {
@@ -27,6 +35,9 @@ pub fn VideoPlayer(
         }
     });
 
+    let game_cursor = expect_context::<RoomManager>().get_game_cursor();
+    let cursors = expect_context::<RoomManager>().get_cursors();
+
     let owner = Owner::current();
     create_effect(move |_| {
         let room_manager = expect_context::<RoomManager>();
@@ -46,6 +57,13 @@ pub fn VideoPlayer(
                             room_manager.send_rtc_message(msg);
                         }
                     });
+                    let (relay_rx, relay_tx) = create_signal(None);
+                    let host_id = host_user.id;
+                    create_effect(move |_| {
+                        if let Some(event) = relay_rx.get() {
+                            expect_context::<RoomManager>().relay_input(host_id, event);
+                        }
+                    });
                     leptos::spawn_local(async move {
                         info!("Connect to host ");
                         if let Err(err) = connect_to_host(
@@ -55,6 +73,8 @@ pub fn VideoPlayer(
                             rtc_message_receiver,
                             rtc_tx,
                             events_rx,
+                            relay_tx,
+                            set_input_transport,
                             owner,
                         )
                         .await
@@ -71,97 +91,57 @@ pub fn VideoPlayer(
         if let Some(canvas) = video_node.get() {
             let el: &web_sys::HtmlElement = canvas.as_ref();
             let el_html: web_sys::HtmlElement = el.clone();
-            let _ = use_event_listener_with_options(
-                el_html.clone(),
-                leptos::ev::touchstart,
-                move |ev| {
-                    // info!("Recceived start");
-                    if let Some(canvas) = video_node.get_untracked() {
-                        if let Some(touch) = ev.changed_touches().item(0) {
-                            let dpr = window().device_pixel_ratio();
-
-                            let rect = canvas.get_bounding_client_rect();
-                            let (x, y) = (f64::from(touch.client_x()), f64::from(touch.client_y()));
-
-                            if is_point_in_rect((x, y), rect.clone()) {
-                                let x = x - rect.left();
-                                let y = y - rect.top();
-                                events_tx.set(Some(KeyEvent::MouseDown(
-                                    x * dpr / rect.width(),
-                                    y * dpr / rect.height(),
-                                )));
-
-                                ev.prevent_default();
-                            }
-                        }
-                    }
-                },
-                UseEventListenerOptions::default().passive(false),
-            );
-
-            let _ = use_event_listener_with_options(
-                el_html.clone(),
-                leptos::ev::touchend,
-                move |ev| {
-                    // info!("Recceived end");
-                    if let Some(canvas) = video_node.get_untracked() {
-                        if let Some(touch) = ev.changed_touches().item(0) {
-                            let dpr = window().device_pixel_ratio();
-
-                            let rect = canvas.get_bounding_client_rect();
-                            let (x, y) = (f64::from(touch.client_x()), f64::from(touch.client_y()));
-
-                            if is_point_in_rect((x, y), rect.clone()) {
-                                let x = x - rect.left();
-                                let y = y - rect.top();
-                                events_tx.set(Some(KeyEvent::MouseUp(
-                                    x * dpr / rect.width(),
-                                    y * dpr / rect.height(),
-                                )));
-
-                                ev.prevent_default();
-                            }
-                        }
-                    }
-                },
-                UseEventListenerOptions::default().passive(false),
-            );
-
-            let _ = use_event_listener_with_options(
-                el_html,
-                leptos::ev::touchmove,
-                move |ev| {
-                    if let Some(canvas) = video_node.get_untracked() {
-                        if let Some(touch) = ev.changed_touches().item(0) {
-                            let dpr = window().device_pixel_ratio();
-
-                            let rect = canvas.get_bounding_client_rect();
-                            let (x, y) = (f64::from(touch.client_x()), f64::from(touch.client_y()));
-
-                            if is_point_in_rect((x, y), rect.clone()) {
-                                let x = x - rect.left();
-                                let y = y - rect.top();
-                                events_tx.set(Some(KeyEvent::MouseMove(
-                                    x * dpr / rect.width(),
-                                    y * dpr / rect.height(),
-                                )));
-
-                                ev.prevent_default();
-                            }
-                        }
-                    }
-                },
-                UseEventListenerOptions::default().passive(false),
-            );
+            use_touch_input(el_html, |el| video_area(el.unchecked_ref()), events_tx);
         }
     });
 
+    let game_area = Signal::derive(move || {
+        video_node
+            .get()
+            .map(|video| {
+                // Letterboxed area relative to the video element
+                let area = video_area(&video);
+                let rect = video.get_bounding_client_rect();
+                Rect {
+                    left: area.left - rect.left(),
+                    top: area.top - rect.top(),
+                    ..area
+                }
+            })
+            .unwrap_or(Rect {
+                left: 0.0,
+                top: 0.0,
+                width: 0.0,
+                height: 0.0,
+            })
+    });
+
     view! {
         <div  class="h-full w-full flex flex-col">
             <div class="h-full w-full absolute flex items-center justify-center">
                 <div class="text-lg"> "Please Wait.." </div>
             </div>
             <div class="flex-1 overflow-auto w-full relative" >
+                <div
+                    class="absolute left-1 top-1 z-10 px-1 text-xs bg-black/60 pointer-events-none"
+                    class=("text-yellow-400", move || input_transport.get() == InputTransport::WebSocket)
+                    title=move || match input_transport.get() {
+                        InputTransport::DataChannel => "Input sent directly to host",
+                        InputTransport::WebSocket => "Input relayed through server",
+                    }
+                >
+                    {move || input_transport.get().label()}
+                </div>
+                <div class="absolute right-1 top-1 z-10">
+                    <PointerLockButton target=Signal::derive(move || {
+                        video_node.get().map(|video| {
+                            let el: &Element = video.as_ref();
+                            el.clone()
+                        })
+                    }) />
+                </div>
+                <GameCursor position=game_cursor area=game_area />
+                <RemoteCursors cursors area=game_area />
                 <video
                     ref=video_node
                     class="h-full w-full"
@@ -172,36 +152,66 @@ pub fn VideoPlayer(
                     tabindex="1"
 
                     on:mousemove=move|ev|{
-                        if let Some(canvas) = video_node.get_untracked(){
-                            let rect = canvas.get_bounding_client_rect();
-                            let dpr = window().device_pixel_ratio();
-                            events_tx.set(Some(KeyEvent::MouseMove(f64::from(ev.offset_x())*dpr/rect.width(), f64::from(ev.offset_y())*dpr/rect.height())));
+                        if let Some(video) = video_node.get_untracked(){
+                            let area = video_area(&video);
+                            if is_pointer_locked(&video) {
+                                events_tx.set(Some(KeyEvent::MouseMoveBy(
+                                    f64::from(ev.movement_x()) / area.width,
+                                    f64::from(ev.movement_y()) / area.height,
+                                )));
+                            } else if let Some((x, y)) = normalize(client_position(&ev), area) {
+                                events_tx.set(Some(KeyEvent::MouseMove(x, y)));
+                            }
                         }
                     }
                     on:mousedown=move|ev|{
-                        if let Some(canvas) = video_node.get_untracked(){
-                            let rect = canvas.get_bounding_client_rect();
-                            let dpr = window().device_pixel_ratio();
-                            events_tx.set(Some(KeyEvent::MouseDown(f64::from(ev.offset_x())*dpr/rect.width(), f64::from(ev.offset_y())*dpr/rect.height())));
+                        if let (Some(video), Some(button)) = (video_node.get_untracked(), MouseButton::from_event(&ev)){
+                            if is_pointer_locked(&video) {
+                                events_tx.set(Some(KeyEvent::CursorDown(button)));
+                            } else if let Some((x, y)) = normalize(client_position(&ev), video_area(&video)) {
+                                events_tx.set(Some(KeyEvent::MouseDown(x, y, button)));
+                            }
                         }
                     }
                     on:mouseup=move|ev|{
-                        if let Some(canvas) = video_node.get_untracked(){
-                            let rect = canvas.get_bounding_client_rect();
-                            let dpr = window().device_pixel_ratio();
-                            events_tx.set(Some(KeyEvent::MouseUp(f64::from(ev.offset_x())*dpr/rect.width(), f64::from(ev.offset_y())*dpr/rect.height())));
+                        if let (Some(video), Some(button)) = (video_node.get_untracked(), MouseButton::from_event(&ev)){
+                            if is_pointer_locked(&video) {
+                                events_tx.set(Some(KeyEvent::CursorUp(button)));
+                            } else {
+                                let (x, y) = normalize_clamped(client_position(&ev), video_area(&video));
+                                events_tx.set(Some(KeyEvent::MouseUp(x, y, button)));
+                            }
+                        }
+                    }
+                    on:wheel=move|ev|{
+                        if let Some(delta) = WheelDelta::from_event(&ev){
+                            ev.prevent_default();
+                            events_tx.set(Some(KeyEvent::MouseWheel(delta)));
                         }
                     }
+                    on:mouseleave=move|_|{
+                        events_tx.set(Some(KeyEvent::MouseLeave));
+                    }
+                    on:contextmenu=move|ev|{
+                        // Right clicks belong to the game
+                        ev.prevent_default();
+                    }
 
                     on:keydown=move|ev|{
-                        if let Ok(key) = Key::try_from(ev){
-                            events_tx.set(Some(KeyEvent::Down(key)));
+                        let key_char = key_char(&ev);
+                        if let Ok(key) = Key::try_from(ev.clone()){
+                            if key == Key::Tab {
+                                // Keep focus on the game
+                                ev.prevent_default();
+                            }
+                            events_tx.set(Some(KeyEvent::Down(key, key_char)));
                         }
                     }
 
                     on:keyup=move|ev|{
-                        if let Ok(key) = Key::try_from(ev){
-                            events_tx.set(Some(KeyEvent::Up(key)));
+                        let key_char = key_char(&ev);
+                        if let Ok(key) = Key::try_from(ev.clone()){
+                            events_tx.set(Some(KeyEvent::Up(key, key_char)));
                         }
                     }
                 />
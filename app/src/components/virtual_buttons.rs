@@ -4,6 +4,7 @@ use logging::warn;
 use tracing::info;
 
 use crate::components::gamepad::Gamepad;
+use crate::components::native_gamepad::NativeGamepads;
 use crate::components::portal::Portal;
 use crate::utils::keycode::KeyEvent;
 use crate::MountPoints;
@@ -20,6 +21,7 @@ pub fn VirtualButtons(
     } = expect_context::<MountPoints>();
 
     let (is_fullscreen, set_is_fullscreen) = create_signal(false);
+    let controller_settings_open = create_rw_signal(false);
 
     create_effect(move |_| {
         info!("Register fullscreenchange");
@@ -38,6 +40,7 @@ pub fn VirtualButtons(
     });
     view! {
         <Gamepad keys_sender=event_sender />
+        <NativeGamepads keys_sender=event_sender settings_open=controller_settings_open />
 
         {
             move || {
@@ -47,7 +50,7 @@ pub fn VirtualButtons(
                     view! {
                         <Portal
                             mount=el.clone()
-                            class="h-full w-full bg-black p-2"
+                            class="h-full w-full bg-black p-2 flex flex-col items-start"
                         >
                             <button class="text-sm"
                                 type="button"
@@ -68,6 +71,14 @@ pub fn VirtualButtons(
                             >
                                 "Full Screen"
                             </button>
+                            <button class="text-sm"
+                                type="button"
+                                on:click=move|_|{
+                                    controller_settings_open.set(true);
+                                }
+                            >
+                                "Controllers"
+                            </button>
                         </Portal>
                     }
                 }else{
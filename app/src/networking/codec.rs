@@ -0,0 +1,24 @@
+use codee::{Decoder, Encoder};
+use common::protocol::{self, DecodeError, EncodeError};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Codec for the room websocket, see [`common::protocol`] for the format.
+pub struct ProtocolCodec;
+
+impl<T: Serialize> Encoder<T> for ProtocolCodec {
+    type Error = EncodeError;
+    type Encoded = Vec<u8>;
+
+    fn encode(val: &T) -> Result<Self::Encoded, Self::Error> {
+        protocol::encode(val)
+    }
+}
+
+impl<T: DeserializeOwned> Decoder<T> for ProtocolCodec {
+    type Error = DecodeError;
+    type Encoded = [u8];
+
+    fn decode(val: &Self::Encoded) -> Result<T, Self::Error> {
+        protocol::decode(val)
+    }
+}
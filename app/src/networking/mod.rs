@@ -1,2 +1,5 @@
+pub mod codec;
+pub mod room_list;
 pub mod room_manager;
 pub mod rtc_connect;
+pub mod send_queue;
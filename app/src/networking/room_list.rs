@@ -0,0 +1,21 @@
+use common::{endpoints, room_list::PublicRoom};
+use leptos::window;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::Response;
+
+/// Rooms listed by the server, the most players first.
+pub async fn fetch_public_rooms() -> Result<Vec<PublicRoom>, JsValue> {
+    let response = JsFuture::from(window().fetch_with_str(endpoints::ROOM_LIST))
+        .await?
+        .dyn_into::<Response>()?;
+    if !response.ok() {
+        return Err(JsValue::from_str(&format!(
+            "Room list request failed with {}",
+            response.status()
+        )));
+    }
+    let body = JsFuture::from(response.text()?).await?;
+    serde_json::from_str(&body.as_string().unwrap_or_default())
+        .map_err(|err| JsValue::from_str(&err.to_string()))
+}
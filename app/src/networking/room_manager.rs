@@ -1,16 +1,24 @@
-use std::{cell::RefCell, marker::PhantomData, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    marker::PhantomData,
+    rc::Rc,
+    time::Duration,
+};
 
-use codee::binary::BincodeSerdeCodec;
 use common::{
     endpoints,
-    message::{ClientMessage, Message, RTCMessage, RtcConfig, UserJoined, UserLeft},
-    params::{HostParams, JoinParams},
-    PlayerStatus, UserMeta, UserState,
+    message::{
+        ChatContent, ChatEntry, ClientMessage, JoinError, Message, Pong, RTCMessage, RtcConfig,
+        ServerError, UserCursor, UserJoined, UserLeft,
+    },
+    params::{HostParams, InvalidParams, JoinParams},
+    protocol::{Encoding, INCOMPATIBLE_VERSION_CLOSE_CODE, PROTOCOL_VERSION},
+    PlayerStatus, Profile, RoomLimits, UserMeta, UserRole, UserState,
 };
 use leptos::{
-    create_effect, create_signal, logging::warn, store_value, with_owner, Owner, ReadSignal,
-    Signal, SignalGet, SignalGetUntracked, SignalSet, SignalWith, SignalWithUntracked, StoredValue,
-    WriteSignal,
+    create_effect, create_signal, logging::warn, set_interval_with_handle, set_timeout,
+    store_value, window, with_owner, IntervalHandle, Owner, ReadSignal, Signal, SignalGet,
+    SignalGetUntracked, SignalSet, SignalWith, SignalWithUntracked, StoredValue, WriteSignal,
 };
 use leptos_router::use_navigate;
 use leptos_use::{
@@ -19,7 +27,25 @@ use leptos_use::{
 use thiserror::Error;
 use tracing::info;
 use uuid::Uuid;
-use web_sys::WebSocket;
+use web_sys::{js_sys::Date, WebSocket};
+
+use crate::{
+    networking::{
+        codec::ProtocolCodec,
+        send_queue::{OutgoingMessage, SendPriority, SendQueue, SendQueueMetrics, HIGH_WATER_MARK},
+    },
+    utils::{
+        chat_command::{mentions, parse_chat},
+        keycode::KeyEvent,
+    },
+};
+
+/// How long to wait before retrying a flush when the socket is backed up.
+const FLUSH_RETRY: Duration = Duration::from_millis(50);
+/// How often the round trip time to the server is measured.
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+/// How long the room flashes when someone mentions the user.
+const MENTION_FLASH: Duration = Duration::from_secs(3);
 
 #[derive(Clone)]
 pub struct RoomManager {
@@ -29,6 +55,24 @@ pub struct RoomManager {
         ReadSignal<Option<PlayerMessages>>,
         WriteSignal<Option<PlayerMessages>>,
     ),
+    send_queue: Rc<RefCell<SendQueue>>,
+    flush_scheduled: Rc<Cell<bool>>,
+    send_metrics: (ReadSignal<SendQueueMetrics>, WriteSignal<SendQueueMetrics>),
+    last_rtt: Rc<Cell<Option<u32>>>,
+    game_cursor: (
+        ReadSignal<Option<(f64, f64)>>,
+        WriteSignal<Option<(f64, f64)>>,
+    ),
+    cursors: (ReadSignal<Vec<UserCursor>>, WriteSignal<Vec<UserCursor>>),
+    server_error: (
+        ReadSignal<Option<ServerError>>,
+        WriteSignal<Option<ServerError>>,
+    ),
+    join_error: (
+        ReadSignal<Option<JoinError>>,
+        WriteSignal<Option<JoinError>>,
+    ),
+    mentioned: (ReadSignal<bool>, WriteSignal<bool>),
     owner: Owner,
 }
 
@@ -91,10 +135,10 @@ where
     pub connection: WebsocketContext<Tx>,
     pub socket: Signal<Option<WebSocket>>,
     pub ready_state: Signal<ConnectionReadyState>,
-    pub chat_history: StoredValue<Vec<(UserMeta, String)>>,
+    pub chat_history: StoredValue<Vec<ChatEntry>>,
     pub chat_signal: (
-        ReadSignal<Option<(UserMeta, String)>>,
-        WriteSignal<Option<(UserMeta, String)>>,
+        ReadSignal<Option<ChatEntry>>,
+        WriteSignal<Option<ChatEntry>>,
     ),
     pub rtc_message_signal: (
         ReadSignal<Option<RTCMessage>>,
@@ -102,6 +146,25 @@ where
     ),
     pub is_host: bool,
     pub rtc_config: RtcConfig,
+    pub ping_interval: Option<IntervalHandle>,
+}
+
+/// Room [`RoomManager::host_join`] connects to.
+#[derive(Debug, Clone)]
+pub enum RoomTarget {
+    Host(HostOptions),
+    /// An existing room by id
+    Join(String),
+}
+
+/// How the host sets up a new room.
+#[derive(Debug, Clone, Default)]
+pub struct HostOptions {
+    pub limits: RoomLimits,
+    /// Title to list the room under, private when `None`
+    pub public_title: Option<String>,
+    /// Code to host the room under, generated by the server when `None`
+    pub room_code: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -111,6 +174,18 @@ pub struct RoomInfo {
     pub users: Vec<UserMeta>,
     pub is_host: bool,
     pub player_status: PlayerStatus,
+    /// Gamepad layout code recommended by the host
+    pub recommended_layout: Option<String>,
+}
+
+impl RoomInfo {
+    pub fn own_role(&self) -> UserRole {
+        self.users
+            .iter()
+            .find(|u| u.id == self.user_id)
+            .map(|u| u.role)
+            .unwrap_or_default()
+    }
 }
 
 #[derive(Clone)]
@@ -122,7 +197,9 @@ pub enum PlayerMessages {
 }
 
 pub enum SendType {
+    /// Queued until it can be sent, survives while the room is connecting.
     Reliable,
+    /// Sent only if the socket can take it soon, superseded or stale messages are dropped.
     UnReliablle,
 }
 
@@ -135,6 +212,10 @@ pub enum RoomManagerError {
 
     #[error("Param failed to encode")]
     ParamError(#[from] serde_urlencoded::ser::Error),
+    /// Params the server would reject, checked before connecting since a
+    /// refused websocket upgrade isn't visible here
+    #[error(transparent)]
+    InvalidParams(#[from] InvalidParams),
 }
 
 impl RoomManager {
@@ -143,6 +224,15 @@ impl RoomManager {
             state: Rc::new(RefCell::new(RoomState::Disconnected)),
             room_info_signal: create_signal(None),
             player_message_tx: create_signal(None),
+            send_queue: Rc::new(RefCell::new(SendQueue::new())),
+            flush_scheduled: Rc::new(Cell::new(false)),
+            send_metrics: create_signal(SendQueueMetrics::default()),
+            last_rtt: Rc::new(Cell::new(None)),
+            game_cursor: create_signal(None),
+            cursors: create_signal(vec![]),
+            server_error: create_signal(None),
+            join_error: create_signal(None),
+            mentioned: create_signal(false),
             owner,
         }
     }
@@ -155,10 +245,34 @@ impl RoomManager {
         self.player_message_tx.0
     }
 
+    pub fn get_send_metrics(&self) -> ReadSignal<SendQueueMetrics> {
+        self.send_metrics.0
+    }
+
+    /// The host's virtual cursor, set while it's moved by a locked pointer.
+    pub fn get_game_cursor(&self) -> ReadSignal<Option<(f64, f64)>> {
+        self.game_cursor.0
+    }
+
+    /// Players' cursors as last shared by the host.
+    pub fn get_cursors(&self) -> ReadSignal<Vec<UserCursor>> {
+        self.cursors.0
+    }
+
+    /// Last thing the server rejected, cleared when sending chat again.
+    pub fn get_server_error(&self) -> ReadSignal<Option<ServerError>> {
+        self.server_error.0
+    }
+
+    /// Why the last attempt to host or join failed, cleared when trying again.
+    pub fn get_join_error(&self) -> ReadSignal<Option<JoinError>> {
+        self.join_error.0
+    }
+
     pub fn host_join(
         &self,
-        name: String,
-        room_code: Option<String>,
+        profile: Profile,
+        target: RoomTarget,
     ) -> Result<Signal<Option<Message>>, RoomManagerError> {
         with_owner(self.owner, || {
             let owner = self.owner;
@@ -166,38 +280,88 @@ impl RoomManager {
             if !is_disconnected {
                 return Err(RoomManagerError::AlreadyConnectedToRoom);
             }
-            let url = if room_code.is_some() {
-                endpoints::JOIN_ROOM
-            } else {
-                endpoints::HOST_ROOM
+            self.join_error.1.set(None);
+            let url = match target {
+                RoomTarget::Host(_) => endpoints::HOST_ROOM,
+                RoomTarget::Join(_) => endpoints::JOIN_ROOM,
             };
-            let params = {
-                if let Some(room_id) = room_code {
-                    let join_params = JoinParams { name, room_id };
+            let params = match target {
+                RoomTarget::Join(room_id) => {
+                    let join_params = JoinParams {
+                        name: profile.name,
+                        color: profile.color,
+                        emoji: profile.emoji,
+                        room_id,
+                        protocol_version: PROTOCOL_VERSION,
+                        encoding: Encoding::MessagePack,
+                    };
+                    join_params.validate()?;
                     serde_urlencoded::to_string(&join_params)
-                } else {
-                    let host_params = HostParams { name };
+                }
+                RoomTarget::Host(HostOptions {
+                    limits,
+                    public_title,
+                    room_code,
+                }) => {
+                    let host_params = HostParams {
+                        name: profile.name,
+                        color: profile.color,
+                        emoji: profile.emoji,
+                        max_players: Some(limits.max_players),
+                        max_spectators: limits.max_spectators,
+                        public: public_title.is_some(),
+                        title: public_title,
+                        room_code,
+                        protocol_version: PROTOCOL_VERSION,
+                        encoding: Encoding::MessagePack,
+                    };
+                    host_params.validate()?;
                     serde_urlencoded::to_string(&host_params)
                 }
             };
             match params {
                 Ok(params) => {
+                    let join_error = self.join_error;
+                    // Whether the server answered with RoomCreated, RoomJoined
+                    // or JoinFailed, independent of the state the close may
+                    // already have reset
+                    let got_join_reply = Rc::new(Cell::new(false));
+                    let got_join_reply_c = got_join_reply.clone();
                     let UseWebSocketReturn {
                         send,
                         message,
                         ready_state,
                         ws,
                         ..
-                    } = use_websocket_with_options::<Message, Message, BincodeSerdeCodec>(
+                    } = use_websocket_with_options::<Message, Message, ProtocolCodec>(
                         &format!("{url}?{params}"),
                         UseWebSocketOptions::default()
-                            .reconnect_limit(leptos_use::ReconnectLimit::Limited(0)),
+                            .reconnect_limit(leptos_use::ReconnectLimit::Limited(0))
+                            .on_close(move |ev| {
+                                if ev.code() == INCOMPATIBLE_VERSION_CLOSE_CODE {
+                                    warn!("Server rejected client {}", ev.reason());
+                                    if window().confirm_with_message(&ev.reason()).unwrap_or(false)
+                                    {
+                                        let _ = window().location().reload();
+                                    }
+                                } else if !got_join_reply_c.get()
+                                    && join_error.0.get_untracked().is_none()
+                                {
+                                    // Closed without a JoinFailed, the upgrade itself failed
+                                    warn!("Connection closed before joining {}", ev.code());
+                                    join_error.1.set(Some(JoinError::ServerError));
+                                }
+                            }),
                     );
                     let state_c = self.state.clone();
                     let state_c1 = self.state.clone();
                     let room_info_reader = self.room_info_signal.0;
                     let room_info_writer = self.room_info_signal.1;
                     let player_messages_sender = self.player_message_tx.1;
+                    let send_queue = self.send_queue.clone();
+                    let send_metrics_writer = self.send_metrics.1;
+                    let manager = self.clone();
+                    let last_rtt = self.last_rtt.clone();
                     create_effect(move |_| {
                         let ws_state = ready_state.get();
                         info!("WS State change {:#?}", ws_state);
@@ -212,9 +376,24 @@ impl RoomManager {
                             | leptos_use::core::ConnectionReadyState::Closed => {
                                 // close();
                                 let mut state = state_c1.borrow_mut();
+                                if let RoomState::Connected(RoomConnectionInfo {
+                                    ping_interval: Some(ping_interval),
+                                    ..
+                                }) = &*state
+                                {
+                                    ping_interval.clear();
+                                }
                                 *state = RoomState::Disconnected;
                                 drop(state);
+                                last_rtt.set(None);
                                 room_info_writer.set(None);
+
+                                let mut queue = send_queue.borrow_mut();
+                                queue.clear();
+                                let metrics = queue.metrics();
+                                drop(queue);
+                                info!("Send queue stats on disconnect {metrics:?}");
+                                send_metrics_writer.set(metrics);
                             }
                         }
                     });
@@ -225,6 +404,7 @@ impl RoomManager {
                                 Message::ServerMessage(message) => match message {
                                     common::message::ServerMessage::RoomCreated(room_info)
                                     | common::message::ServerMessage::RoomJoined(room_info) => {
+                                        got_join_reply.set(true);
                                         let nav = use_navigate();
                                         let state_c_ref = state_c.borrow();
                                         if let RoomState::Connecting((
@@ -236,30 +416,52 @@ impl RoomManager {
                                             let is_host = room_info.users.first().map(|u| u.id)
                                                 == Some(room_info.user_id);
                                             let rtc_config = room_info.rtc_config;
+                                            let chat_log = room_info.chat_log;
                                             let room_info = RoomInfo {
                                                 id: room_info.room_id.clone(),
                                                 user_id: room_info.user_id,
                                                 users: room_info.users,
                                                 player_status: room_info.player_status,
                                                 is_host,
+                                                recommended_layout: room_info.recommended_layout,
                                             };
 
                                             let chat_signal =
                                                 with_owner(owner, || create_signal(None));
                                             let chat_history =
-                                                with_owner(owner, || store_value(Vec::new()));
+                                                with_owner(owner, || store_value(chat_log));
 
                                             let rtc_signal =
                                                 with_owner(owner, || create_signal(None));
 
                                             with_owner(owner, || {
                                                 create_effect(move |_| {
-                                                    if let Some(msg) = chat_signal.0.get() {
-                                                        chat_history.update_value(|v| v.push(msg));
+                                                    if let Some(entry) = chat_signal.0.get() {
+                                                        chat_history.update_value(|v| {
+                                                            // Skip what the log we joined with already has
+                                                            let is_new = match v.last() {
+                                                                Some(last) => last.id < entry.id,
+                                                                None => true,
+                                                            };
+                                                            if is_new {
+                                                                v.push(entry)
+                                                            }
+                                                        });
                                                     }
                                                 })
                                             });
 
+                                            let ping_interval = with_owner(owner, || {
+                                                let manager = manager.clone();
+                                                set_interval_with_handle(
+                                                    move || manager.send_ping(),
+                                                    PING_INTERVAL,
+                                                )
+                                            });
+                                            if let Err(err) = &ping_interval {
+                                                warn!("Cant start ping interval {err:?}");
+                                            }
+
                                             let connection_info = RoomConnectionInfo {
                                                 connection: unsafe { std::ptr::read(connection) },
                                                 socket: *socket,
@@ -269,6 +471,7 @@ impl RoomManager {
                                                 rtc_message_signal: rtc_signal,
                                                 is_host,
                                                 rtc_config,
+                                                ping_interval: ping_interval.ok(),
                                             };
                                             drop(state_c_ref);
                                             let mut state = state_c.borrow_mut();
@@ -279,6 +482,9 @@ impl RoomManager {
                                                 Default::default(),
                                             );
                                             room_info_writer.set(Some(room_info));
+                                            // Send whatever got queued while connecting
+                                            manager.flush_send_queue();
+                                            manager.send_ping();
                                         }
                                     }
                                     common::message::ServerMessage::UserJoined(UserJoined {
@@ -305,6 +511,65 @@ impl RoomManager {
                                             room_info_writer.set(Some(room_info));
                                         }
                                     }
+                                    common::message::ServerMessage::Chat(entry) => {
+                                        let own = room_info_reader.with_untracked(|r| {
+                                            r.as_ref().and_then(|r| {
+                                                r.users.iter().find(|u| u.id == r.user_id).cloned()
+                                            })
+                                        });
+                                        if let (
+                                            Some(own),
+                                            ChatContent::Message { user_id, text, .. }
+                                            | ChatContent::Action { user_id, text, .. },
+                                        ) = (own, &entry.content)
+                                        {
+                                            if *user_id != own.id && mentions(text, &own.name) {
+                                                manager.flash_mention();
+                                            }
+                                        }
+                                        if let RoomState::Connected(RoomConnectionInfo {
+                                            chat_signal,
+                                            ..
+                                        }) = &*state_c.borrow()
+                                        {
+                                            chat_signal.1.set(Some(entry));
+                                        }
+                                    }
+                                    common::message::ServerMessage::Kicked => {
+                                        warn!("Kicked from the room");
+                                        let _ = window()
+                                            .alert_with_message("You were kicked from the room");
+                                    }
+                                    common::message::ServerMessage::JoinFailed(err) => {
+                                        got_join_reply.set(true);
+                                        warn!("Failed to join {err:?}");
+                                        manager.join_error.1.set(Some(err));
+                                    }
+                                    common::message::ServerMessage::Error(err) => {
+                                        warn!("Server rejected a message {err:?}");
+                                        manager.server_error.1.set(Some(err));
+                                    }
+                                    common::message::ServerMessage::Pong(Pong {
+                                        sent_at,
+                                        latencies,
+                                    }) => {
+                                        let rtt = (Date::now() - sent_at).max(0.0) as u32;
+                                        manager.last_rtt.set(Some(rtt));
+                                        if let Some(mut room_info) =
+                                            room_info_reader.get_untracked()
+                                        {
+                                            for user in room_info.users.iter_mut() {
+                                                if user.id == room_info.user_id {
+                                                    user.latency = Some(rtt);
+                                                } else if let Some((_, latency)) =
+                                                    latencies.iter().find(|(id, _)| *id == user.id)
+                                                {
+                                                    user.latency = *latency;
+                                                }
+                                            }
+                                            room_info_writer.set(Some(room_info));
+                                        }
+                                    }
                                 },
                                 Message::ClientMessage((from_user, message)) => match message {
                                     common::message::ClientMessage::SelectedVideo(video_name) => {
@@ -370,24 +635,61 @@ impl RoomManager {
                                         player_messages_sender
                                             .set(Some(PlayerMessages::Update(time)));
                                     }
-                                    common::message::ClientMessage::Chat(message) => {
-                                        if let RoomState::Connected(RoomConnectionInfo {
-                                            chat_signal,
-                                            ..
-                                        }) = &*state_c.borrow()
+                                    common::message::ClientMessage::Ping(..) => {
+                                        // Only meant for the server
+                                    }
+                                    common::message::ClientMessage::RecommendLayout(code) => {
+                                        if let Some(mut room_info) =
+                                            room_info_reader.get_untracked()
                                         {
-                                            if let Some(user) = room_info_reader.with(|r| {
-                                                r.as_ref().and_then(|r| {
-                                                    r.users
-                                                        .iter()
-                                                        .find(|u| u.id == from_user)
-                                                        .cloned()
-                                                })
-                                            }) {
-                                                chat_signal.1.set(Some((user, message)));
+                                            if room_info.users.first().map(|u| u.id)
+                                                == Some(from_user)
+                                            {
+                                                room_info.recommended_layout = Some(code);
+                                                room_info_writer.set(Some(room_info));
                                             }
                                         }
                                     }
+                                    common::message::ClientMessage::GameCursor(position) => {
+                                        manager.game_cursor.1.set(position);
+                                    }
+                                    common::message::ClientMessage::Cursors(cursors) => {
+                                        manager.cursors.1.set(cursors);
+                                    }
+                                    common::message::ClientMessage::Chat(_)
+                                    | common::message::ClientMessage::Action(_)
+                                    | common::message::ClientMessage::Roll(_)
+                                    | common::message::ClientMessage::Kick(_) => {
+                                        // Chat comes back logged as ServerMessage::Chat
+                                    }
+                                    common::message::ClientMessage::UpdateProfile(profile) => {
+                                        if let Some(mut room_info) =
+                                            room_info_reader.get_untracked()
+                                        {
+                                            if let Some(user) = room_info
+                                                .users
+                                                .iter_mut()
+                                                .find(|u| u.id == from_user)
+                                            {
+                                                user.set_profile(profile);
+                                            }
+                                            room_info_writer.set(Some(room_info));
+                                        }
+                                    }
+                                    common::message::ClientMessage::ClaimSlot(slot) => {
+                                        if let Some(mut room_info) =
+                                            room_info_reader.get_untracked()
+                                        {
+                                            if let Some(user) = room_info
+                                                .users
+                                                .iter_mut()
+                                                .find(|u| u.id == from_user)
+                                            {
+                                                user.slot = slot;
+                                            }
+                                            room_info_writer.set(Some(room_info));
+                                        }
+                                    }
                                 },
                                 Message::RTCMessage(rtc_messages) => {
                                     if let RoomState::Connected(RoomConnectionInfo {
@@ -472,30 +774,93 @@ impl RoomManager {
     }
 
     pub fn send_message(&self, message: ClientMessage, send_type: SendType) {
+        let (priority, reliable) = match send_type {
+            SendType::Reliable => (SendPriority::Normal, true),
+            SendType::UnReliablle => (SendPriority::Low, false),
+        };
+        self.queue_message(OutgoingMessage::Client(message), priority, reliable);
+    }
+
+    /// Sends a ping to measure round trip time, reporting the previous measurement.
+    pub fn send_ping(&self) {
+        self.send_message(
+            ClientMessage::Ping(Date::now(), self.last_rtt.get()),
+            SendType::UnReliablle,
+        );
+    }
+
+    fn queue_message(&self, message: OutgoingMessage, priority: SendPriority, reliable: bool) {
+        if self.state.borrow().is_disconnected() {
+            return;
+        }
+        self.send_queue
+            .borrow_mut()
+            .push(message, priority, reliable, Date::now());
+        self.flush_send_queue();
+    }
+
+    /// Hands queued messages to the websocket until it's drained or backed up,
+    /// retrying later in the latter case.
+    fn flush_send_queue(&self) {
         with_owner(self.owner, || {
             if let Some(player_id) = self
                 .room_info_signal
                 .0
                 .with_untracked(|r| r.as_ref().map(|r| r.user_id))
             {
-                if let RoomState::Connected(RoomConnectionInfo {
-                    connection, socket, ..
-                }) = &*self.state.borrow()
-                {
-                    match send_type {
-                        SendType::Reliable => {
-                            connection.send(Message::ClientMessage((player_id, message)));
-                        }
-                        SendType::UnReliablle => {
-                            if let Some(socket) = socket.get_untracked() {
-                                if socket.buffered_amount() < 5 {
-                                    connection.send(Message::ClientMessage((player_id, message)));
+                let flushed = match &*self.state.borrow() {
+                    RoomState::Connected(RoomConnectionInfo {
+                        connection,
+                        socket,
+                        ready_state,
+                        ..
+                    }) if ready_state.get_untracked() == ConnectionReadyState::Open => {
+                        if let Some(socket) = socket.get_untracked() {
+                            let mut queue = self.send_queue.borrow_mut();
+                            let now = Date::now();
+                            while socket.buffered_amount() < HIGH_WATER_MARK {
+                                match queue.pop(now) {
+                                    Some(OutgoingMessage::Client(message)) => {
+                                        connection
+                                            .send(Message::ClientMessage((player_id, message)));
+                                    }
+                                    Some(OutgoingMessage::Rtc(message)) => {
+                                        connection.send(Message::RTCMessage(message));
+                                    }
+                                    Some(OutgoingMessage::RelayInput(to_user, event)) => {
+                                        match bincode::serialize(&event) {
+                                            Ok(data) => connection.send(Message::RTCMessage(
+                                                RTCMessage::RelayInput(to_user, data),
+                                            )),
+                                            Err(err) => warn!("Cannot encode input {err:?}"),
+                                        }
+                                    }
+                                    None => break,
                                 }
-                            } else {
-                                warn!("Websocket is None");
                             }
+                            Some((queue.metrics(), !queue.is_empty()))
+                        } else {
+                            warn!("Websocket is None");
+                            None
                         }
                     }
+                    _ => None,
+                };
+                // The state and queue are released here, subscribers to the
+                // metrics may send messages themselves
+                if let Some((metrics, pending)) = flushed {
+                    self.send_metrics.1.set(metrics);
+                    if pending && !self.flush_scheduled.get() {
+                        self.flush_scheduled.set(true);
+                        let manager = self.clone();
+                        set_timeout(
+                            move || {
+                                manager.flush_scheduled.set(false);
+                                manager.flush_send_queue();
+                            },
+                            FLUSH_RETRY,
+                        );
+                    }
                 }
             }
         })
@@ -503,10 +868,7 @@ impl RoomManager {
 
     pub fn get_chat_signal(
         &self,
-    ) -> Option<(
-        ReadSignal<Option<(UserMeta, String)>>,
-        StoredValue<Vec<(UserMeta, String)>>,
-    )> {
+    ) -> Option<(ReadSignal<Option<ChatEntry>>, StoredValue<Vec<ChatEntry>>)> {
         if let RoomState::Connected(RoomConnectionInfo {
             chat_history,
             chat_signal,
@@ -519,33 +881,89 @@ impl RoomManager {
         }
     }
 
-    pub fn send_chat(&self, msg: String) {
+    /// Sends what was typed in chat, `Err` explains a command that can't be
+    /// sent as typed.
+    pub fn send_chat(&self, msg: String) -> Result<(), String> {
         if msg.trim().is_empty() {
-            return;
+            return Ok(());
         }
-        if let Some(user) = self.room_info_signal.0.with(|r| {
-            r.as_ref()
-                .and_then(|r| r.users.iter().find(|u| u.id == r.user_id).cloned())
-        }) {
-            {
-                if let RoomState::Connected(RoomConnectionInfo { chat_signal, .. }) =
-                    &*self.state.borrow()
-                {
-                    chat_signal.1.set(Some((user, msg.clone())));
-                }
+        let Some((users, own_id)) = self
+            .room_info_signal
+            .0
+            .with_untracked(|r| r.as_ref().map(|r| (r.users.clone(), r.user_id)))
+        else {
+            return Err("Not in a room".to_string());
+        };
+        let message = parse_chat(&msg, &users, own_id)?;
+        self.server_error.1.set(None);
+        // Shown once the server has logged it
+        self.send_message(message, SendType::Reliable);
+        Ok(())
+    }
+
+    /// Asks to change the user's name, color or avatar, everyone including
+    /// the user sees it once the server accepted it.
+    pub fn update_profile(&self, profile: Profile) {
+        self.server_error.1.set(None);
+        self.send_message(ClientMessage::UpdateProfile(profile), SendType::Reliable);
+    }
+
+    /// Whether someone just mentioned the user in chat.
+    pub fn get_mentioned(&self) -> ReadSignal<bool> {
+        self.mentioned.0
+    }
+
+    fn flash_mention(&self) {
+        self.mentioned.1.set(true);
+        let set_mentioned = self.mentioned.1;
+        set_timeout(move || set_mentioned.set(false), MENTION_FLASH);
+    }
+
+    /// Shares a gamepad layout code with the room, only the host's is accepted.
+    pub fn recommend_layout(&self, code: String) {
+        if let Some(mut room_info) = self.room_info_signal.0.get_untracked() {
+            if room_info.is_host {
+                room_info.recommended_layout = Some(code.clone());
+                self.room_info_signal.1.set(Some(room_info));
+                self.send_message(ClientMessage::RecommendLayout(code), SendType::Reliable);
             }
-            self.send_message(ClientMessage::Chat(msg), SendType::Reliable);
         }
     }
 
+    /// Shows the host's virtual cursor to the room.
+    pub fn share_game_cursor(&self, position: Option<(f64, f64)>) {
+        // Hiding it must not get lost
+        let send_type = if position.is_some() {
+            SendType::UnReliablle
+        } else {
+            SendType::Reliable
+        };
+        self.send_message(ClientMessage::GameCursor(position), send_type);
+    }
+
+    /// Shows where every player points to the room.
+    pub fn share_cursors(&self, cursors: Vec<UserCursor>) {
+        let send_type = if cursors.is_empty() {
+            SendType::Reliable
+        } else {
+            SendType::UnReliablle
+        };
+        self.send_message(ClientMessage::Cursors(cursors), send_type);
+    }
+
     pub fn send_rtc_message(&self, message: RTCMessage) {
-        with_owner(self.owner, || {
-            if let RoomState::Connected(RoomConnectionInfo { connection, .. }) =
-                &*self.state.borrow()
-            {
-                connection.send(Message::RTCMessage(message));
-            }
-        })
+        self.queue_message(OutgoingMessage::Rtc(message), SendPriority::High, true);
+    }
+
+    /// Sends input to `to_user` through the server when the data channel is down.
+    pub fn relay_input(&self, to_user: Uuid, event: KeyEvent) {
+        // As lossy as the data channel it stands in for, except releases
+        let reliable = event.is_release();
+        self.queue_message(
+            OutgoingMessage::RelayInput(to_user, event),
+            SendPriority::High,
+            reliable,
+        );
     }
 
     pub fn get_rtc_signal(&self) -> Option<ReadSignal<Option<RTCMessage>>> {
@@ -12,12 +12,31 @@ use wasm_bindgen::{JsCast, JsValue};
 use web_sys::{
     js_sys::{Array, ArrayBuffer, Uint8Array, JSON},
     Blob, MediaStream, MessageEvent, RtcConfiguration, RtcDataChannelEvent, RtcDataChannelInit,
-    RtcIceCandidate, RtcIceCandidateInit, RtcIceServer, RtcOfferOptions, RtcPeerConnection,
-    RtcPeerConnectionIceEvent, RtcSdpType, RtcSessionDescriptionInit, RtcTrackEvent,
+    RtcDataChannelState, RtcIceCandidate, RtcIceCandidateInit, RtcIceServer, RtcOfferOptions,
+    RtcPeerConnection, RtcPeerConnectionIceEvent, RtcSdpType, RtcSessionDescriptionInit,
+    RtcTrackEvent,
 };
 
 use crate::utils::keycode::KeyEvent;
 
+/// Path the guest's input currently takes to reach the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputTransport {
+    /// Peer to peer over the `events` data channel.
+    DataChannel,
+    /// Relayed by the server over the room websocket.
+    WebSocket,
+}
+
+impl InputTransport {
+    pub fn label(&self) -> &'static str {
+        match self {
+            InputTransport::DataChannel => "P2P",
+            InputTransport::WebSocket => "RELAY",
+        }
+    }
+}
+
 pub fn connect_rtc(rtc_config: &RtcConfig) -> Result<RtcPeerConnection, JsValue> {
     warn!("CREATING PC");
     RtcPeerConnection::new_with_configuration(&{
@@ -42,6 +61,7 @@ pub fn connect_rtc(rtc_config: &RtcConfig) -> Result<RtcPeerConnection, JsValue>
     })
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn connect_to_host(
     host_user: Uuid,
     rtc_config: &RtcConfig,
@@ -49,6 +69,8 @@ pub async fn connect_to_host(
     rtc_message_receiver: ReadSignal<Option<RTCMessage>>,
     rtc_message_sender: WriteSignal<Option<RTCMessage>>,
     events_rx: ReadSignal<Option<KeyEvent>>,
+    relay_sender: WriteSignal<Option<KeyEvent>>,
+    transport_setter: WriteSignal<InputTransport>,
     owner: Owner,
 ) -> Result<(), JsValue> {
     let pc = connect_rtc(rtc_config)?;
@@ -77,14 +99,35 @@ pub async fn connect_to_host(
     });
 
     with_owner(owner, || {
+        let _ = use_event_listener(
+            dc.clone(),
+            ev::Custom::<web_sys::Event>::new("open"),
+            move |_| {
+                info!("Events data channel open");
+                transport_setter.set(InputTransport::DataChannel);
+            },
+        );
+        let _ = use_event_listener(
+            dc.clone(),
+            ev::Custom::<web_sys::Event>::new("close"),
+            move |_| {
+                warn!("Events data channel closed, relaying input over websocket");
+                transport_setter.set(InputTransport::WebSocket);
+            },
+        );
         create_effect(move |_| {
             if let Some(ev) = events_rx.get() {
-                let data = bincode::serialize(&ev);
-                if let Ok(data) = data {
-                    if let Err(err) = dc.send_with_u8_array(&data) {
-                        warn!("Failed to send to data_channel {err:?}")
+                if dc.ready_state() == RtcDataChannelState::Open {
+                    if let Ok(data) = bincode::serialize(&ev) {
+                        match dc.send_with_u8_array(&data) {
+                            Ok(_) => return,
+                            Err(err) => warn!("Failed to send to data_channel {err:?}"),
+                        }
                     }
                 }
+                // Data channel isn't usable (ICE never connected or it dropped),
+                // let the server relay the input to the host instead.
+                relay_sender.set(Some(ev));
             }
         });
     });
@@ -159,6 +202,9 @@ pub async fn connect_to_host(
                                 warn!("Cant deserialize candidate")
                             }
                         }
+                        RTCMessage::RelayInput(..) => {
+                            // Only hosts consume relayed input
+                        }
                     }
                 }
             }
@@ -174,6 +220,7 @@ pub fn receive_peer_connections(
     rtc_message_receiver: ReadSignal<Option<RTCMessage>>,
     rtc_message_sender: WriteSignal<Option<RTCMessage>>,
     events_tx: WriteSignal<Option<KeyEvent>>,
+    pointer_tx: WriteSignal<Option<(Uuid, Option<(f64, f64)>)>>,
 ) {
     let peers = store_value(HashMap::new());
     let pending_candidates = store_value(HashMap::<Uuid, Vec<RtcIceCandidateInit>>::new());
@@ -234,6 +281,9 @@ pub fn receive_peer_connections(
                                                                     &data_vec,
                                                                 )
                                                             {
+                                                                report_pointer(
+                                                                    pointer_tx, from_user, &data,
+                                                                );
                                                                 events_tx.set(Some(data));
                                                             } else {
                                                                 warn!("ev not keyevent")
@@ -311,11 +361,32 @@ pub fn receive_peer_connections(
                         warn!("Cant deserialize candidate")
                     }
                 }
+                RTCMessage::RelayInput(from_user, data) => {
+                    if let Ok(data) = bincode::deserialize::<KeyEvent>(&data) {
+                        report_pointer(pointer_tx, from_user, &data);
+                        events_tx.set(Some(data));
+                    } else {
+                        warn!("relayed input not keyevent")
+                    }
+                }
             }
         }
     });
 }
 
+/// Tells where `user` points, `None` once their mouse left the game.
+fn report_pointer(
+    pointer_tx: WriteSignal<Option<(Uuid, Option<(f64, f64)>)>>,
+    user: Uuid,
+    event: &KeyEvent,
+) {
+    if let Some(position) = event.position() {
+        pointer_tx.set(Some((user, Some(position))));
+    } else if *event == KeyEvent::MouseLeave {
+        pointer_tx.set(Some((user, None)));
+    }
+}
+
 async fn accept_peer_connection(
     rtc_config: &RtcConfig,
     rtc_session_desc: RTCSessionDesc,
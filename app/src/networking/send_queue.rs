@@ -0,0 +1,308 @@
+use std::collections::VecDeque;
+
+use common::message::{ClientMessage, RTCMessage};
+use uuid::Uuid;
+
+use crate::utils::keycode::KeyEvent;
+
+/// Bytes buffered on the websocket above which we stop handing it more data.
+pub const HIGH_WATER_MARK: u32 = 64 * 1024;
+
+/// Unreliable messages that waited longer than this (ms) are stale, drop them.
+const UNRELIABLE_TTL_MS: f64 = 500.0;
+/// Max unreliable messages kept around per priority, oldest get dropped first.
+const MAX_UNRELIABLE: usize = 32;
+/// Max reliable messages kept around per priority, oldest get dropped first.
+const MAX_RELIABLE: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SendPriority {
+    Low = 0,
+    Normal = 1,
+    High = 2,
+}
+
+#[derive(Debug, Clone)]
+pub enum OutgoingMessage {
+    Client(ClientMessage),
+    Rtc(RTCMessage),
+    /// Input relayed to a user when the data channel is down. Encoded when
+    /// it's sent so superseded moves never are.
+    RelayInput(Uuid, KeyEvent),
+}
+
+impl OutgoingMessage {
+    /// Messages with the same key supersede each other, only the latest one
+    /// is worth sending.
+    fn coalesce_key(&self) -> Option<CoalesceKey> {
+        match self {
+            OutgoingMessage::Client(ClientMessage::Update(_)) => Some(CoalesceKey::Update),
+            OutgoingMessage::Client(ClientMessage::Seek(_)) => Some(CoalesceKey::Seek),
+            OutgoingMessage::Client(ClientMessage::GameCursor(_)) => Some(CoalesceKey::GameCursor),
+            OutgoingMessage::Client(ClientMessage::Cursors(_)) => Some(CoalesceKey::Cursors),
+            OutgoingMessage::RelayInput(to_user, KeyEvent::MouseMove(..)) => {
+                Some(CoalesceKey::MouseMove(*to_user))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CoalesceKey {
+    Update,
+    Seek,
+    GameCursor,
+    Cursors,
+    MouseMove(Uuid),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SendQueueMetrics {
+    pub queued: usize,
+    pub sent: u64,
+    pub dropped: u64,
+    pub coalesced: u64,
+}
+
+struct QueuedMessage {
+    message: OutgoingMessage,
+    /// Worked out once when queued, compared on every push after it
+    coalesce_key: Option<CoalesceKey>,
+    reliable: bool,
+    queued_at: f64,
+}
+
+/// Outbound messages waiting for the room websocket, drained highest
+/// priority first and in order within a priority.
+pub struct SendQueue {
+    queues: [VecDeque<QueuedMessage>; 3],
+    metrics: SendQueueMetrics,
+}
+
+impl SendQueue {
+    pub fn new() -> Self {
+        Self {
+            queues: Default::default(),
+            metrics: SendQueueMetrics::default(),
+        }
+    }
+
+    pub fn push(
+        &mut self,
+        message: OutgoingMessage,
+        priority: SendPriority,
+        reliable: bool,
+        now: f64,
+    ) {
+        let queue = &mut self.queues[priority as usize];
+        let coalesce_key = message.coalesce_key();
+
+        if let (Some(key), Some(last)) = (coalesce_key, queue.back_mut()) {
+            if last.coalesce_key == Some(key) {
+                last.message = message;
+                last.reliable = reliable;
+                last.queued_at = now;
+                self.metrics.coalesced += 1;
+                return;
+            }
+        }
+
+        let limit = if reliable {
+            MAX_RELIABLE
+        } else {
+            MAX_UNRELIABLE
+        };
+        if queue.iter().filter(|m| m.reliable == reliable).count() >= limit {
+            if let Some(index) = queue.iter().position(|m| m.reliable == reliable) {
+                queue.remove(index);
+                self.metrics.dropped += 1;
+            }
+        }
+
+        queue.push_back(QueuedMessage {
+            message,
+            coalesce_key,
+            reliable,
+            queued_at: now,
+        });
+        self.update_queued();
+    }
+
+    /// Takes the next message to send, dropping stale unreliable ones on the way.
+    pub fn pop(&mut self, now: f64) -> Option<OutgoingMessage> {
+        let mut next = None;
+        for queue in self.queues.iter_mut().rev() {
+            while let Some(queued) = queue.pop_front() {
+                if !queued.reliable && now - queued.queued_at > UNRELIABLE_TTL_MS {
+                    self.metrics.dropped += 1;
+                    continue;
+                }
+                next = Some(queued.message);
+                break;
+            }
+            if next.is_some() {
+                break;
+            }
+        }
+        if next.is_some() {
+            self.metrics.sent += 1;
+        }
+        self.update_queued();
+        next
+    }
+
+    pub fn clear(&mut self) {
+        for queue in self.queues.iter_mut() {
+            self.metrics.dropped += queue.len() as u64;
+            queue.clear();
+        }
+        self.update_queued();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queues.iter().all(|q| q.is_empty())
+    }
+
+    pub fn metrics(&self) -> SendQueueMetrics {
+        self.metrics
+    }
+
+    fn update_queued(&mut self) {
+        self.metrics.queued = self.queues.iter().map(|q| q.len()).sum();
+    }
+}
+
+impl Default for SendQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::keycode::Key;
+
+    fn update(time: f64) -> OutgoingMessage {
+        OutgoingMessage::Client(ClientMessage::Update(time))
+    }
+
+    fn chat(text: &str) -> OutgoingMessage {
+        OutgoingMessage::Client(ClientMessage::Chat(text.to_string()))
+    }
+
+    fn popped_chat(queue: &mut SendQueue, now: f64) -> Option<String> {
+        match queue.pop(now) {
+            Some(OutgoingMessage::Client(ClientMessage::Chat(text))) => Some(text),
+            other => panic!("expected chat, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn pops_highest_priority_first_in_order() {
+        let mut queue = SendQueue::new();
+        queue.push(chat("low"), SendPriority::Low, true, 0.0);
+        queue.push(chat("normal 1"), SendPriority::Normal, true, 0.0);
+        queue.push(chat("high"), SendPriority::High, true, 0.0);
+        queue.push(chat("normal 2"), SendPriority::Normal, true, 0.0);
+
+        assert_eq!(popped_chat(&mut queue, 0.0).as_deref(), Some("high"));
+        assert_eq!(popped_chat(&mut queue, 0.0).as_deref(), Some("normal 1"));
+        assert_eq!(popped_chat(&mut queue, 0.0).as_deref(), Some("normal 2"));
+        assert_eq!(popped_chat(&mut queue, 0.0).as_deref(), Some("low"));
+        assert!(queue.pop(0.0).is_none());
+        assert_eq!(queue.metrics().sent, 4);
+    }
+
+    #[test]
+    fn coalesces_with_the_last_message_only() {
+        let mut queue = SendQueue::new();
+        queue.push(update(1.0), SendPriority::Low, false, 0.0);
+        queue.push(update(2.0), SendPriority::Low, false, 0.0);
+        queue.push(chat("hi"), SendPriority::Low, false, 0.0);
+        queue.push(update(3.0), SendPriority::Low, false, 0.0);
+
+        assert_eq!(queue.metrics().coalesced, 1);
+        assert_eq!(queue.metrics().queued, 3);
+        assert!(matches!(
+            queue.pop(0.0),
+            Some(OutgoingMessage::Client(ClientMessage::Update(time))) if time == 2.0
+        ));
+        assert_eq!(popped_chat(&mut queue, 0.0).as_deref(), Some("hi"));
+        assert!(matches!(
+            queue.pop(0.0),
+            Some(OutgoingMessage::Client(ClientMessage::Update(time))) if time == 3.0
+        ));
+    }
+
+    #[test]
+    fn coalesces_relayed_moves_per_user() {
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut queue = SendQueue::new();
+        let relay = |to_user, event| OutgoingMessage::RelayInput(to_user, event);
+        queue.push(
+            relay(alice, KeyEvent::MouseMove(0.1, 0.1)),
+            SendPriority::High,
+            false,
+            0.0,
+        );
+        queue.push(
+            relay(alice, KeyEvent::MouseMove(0.2, 0.2)),
+            SendPriority::High,
+            false,
+            0.0,
+        );
+        queue.push(
+            relay(bob, KeyEvent::MouseMove(0.3, 0.3)),
+            SendPriority::High,
+            false,
+            0.0,
+        );
+        queue.push(
+            relay(bob, KeyEvent::Down(Key::A, Some('a'))),
+            SendPriority::High,
+            false,
+            0.0,
+        );
+        queue.push(
+            relay(bob, KeyEvent::Down(Key::A, Some('a'))),
+            SendPriority::High,
+            false,
+            0.0,
+        );
+
+        assert_eq!(queue.metrics().coalesced, 1);
+        assert_eq!(queue.metrics().queued, 4);
+        assert!(matches!(
+            queue.pop(0.0),
+            Some(OutgoingMessage::RelayInput(to_user, KeyEvent::MouseMove(x, _))) if to_user == alice && x == 0.2
+        ));
+    }
+
+    #[test]
+    fn drops_stale_unreliable_messages() {
+        let mut queue = SendQueue::new();
+        queue.push(chat("stale"), SendPriority::Normal, false, 0.0);
+        queue.push(chat("kept"), SendPriority::Normal, true, 0.0);
+        queue.push(chat("fresh"), SendPriority::Normal, false, 400.0);
+
+        let now = UNRELIABLE_TTL_MS + 100.0;
+        assert_eq!(popped_chat(&mut queue, now).as_deref(), Some("kept"));
+        assert_eq!(popped_chat(&mut queue, now).as_deref(), Some("fresh"));
+        assert!(queue.pop(now).is_none());
+        assert_eq!(queue.metrics().dropped, 1);
+    }
+
+    #[test]
+    fn drops_oldest_unreliable_over_the_cap() {
+        let mut queue = SendQueue::new();
+        for i in 0..=MAX_UNRELIABLE {
+            queue.push(chat(&i.to_string()), SendPriority::Low, false, 0.0);
+        }
+
+        assert_eq!(queue.metrics().dropped, 1);
+        assert_eq!(queue.metrics().queued, MAX_UNRELIABLE);
+        assert_eq!(popped_chat(&mut queue, 0.0).as_deref(), Some("1"));
+    }
+}
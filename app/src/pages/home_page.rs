@@ -1,15 +1,59 @@
+use common::{Profile, MAX_PLAYERS, MAX_ROOM_ID_LEN, MAX_TITLE_LEN};
 use leptos::component;
 use leptos::*;
 use logging::warn;
 
 use crate::components::dialog::Dialog;
-use crate::networking::room_manager::RoomManager;
+use crate::components::profile::ProfileFields;
+use crate::components::room_list::RoomList;
+use crate::networking::room_manager::{HostOptions, RoomManager, RoomManagerError, RoomTarget};
+use crate::utils::profile::use_stored_profile;
+
+/// Connects with `profile`, returning what to show in the dialog when it
+/// can't. The profile is remembered once it's accepted.
+pub fn connect(
+    profile: Profile,
+    target: RoomTarget,
+    set_stored_profile: WriteSignal<Profile>,
+) -> Vec<String> {
+    let room_manager = expect_context::<RoomManager>();
+    match room_manager.host_join(profile.clone(), target) {
+        Ok(_) => {
+            set_stored_profile.set(profile);
+            Vec::new()
+        }
+        Err(RoomManagerError::InvalidParams(invalid)) => {
+            invalid.errors.iter().map(ToString::to_string).collect()
+        }
+        Err(err) => {
+            warn!("Cannot join {err:#?}");
+            vec![err.to_string()]
+        }
+    }
+}
+
+/// Invalid params, or why the server turned the last attempt down.
+#[component]
+pub fn DialogErrors(errors: ReadSignal<Vec<String>>) -> impl IntoView {
+    let join_error = expect_context::<RoomManager>().get_join_error();
+    view! {
+        <For each=move || errors.get() key=|error| error.clone() let:error>
+            <div class="text-xs text-red-400">{error}</div>
+        </For>
+        <div class="text-xs text-red-400">
+            {move || join_error.get().map(|err| err.to_string())}
+        </div>
+    }
+}
 
 /// Renders the home page of your application.
 #[component]
 pub fn HomePage() -> impl IntoView {
     let (host_open, set_host_open) = create_signal(false);
     let (join_open, set_join_open) = create_signal(false);
+    let (stored_profile, set_stored_profile) = use_stored_profile();
+    // Shared with the room list, picking a room fills it in
+    let (room_code, set_room_code) = create_signal(String::new());
 
     view! {
         <Dialog
@@ -19,42 +63,114 @@ pub fn HomePage() -> impl IntoView {
             }
         >
             {{
-                let (name, set_name) = create_signal(String::new());
+                let profile = create_rw_signal(Profile::default());
+                // Prefill with the profile used last time
+                create_effect(move |_| profile.set(stored_profile.get()));
+                let (errors, set_errors) = create_signal(Vec::new());
+                let options = create_rw_signal(HostOptions::default());
                 view! {
                     <h3 class="font-bold2  text-xl text-center w-full">"Host"</h3>
 
                     <div class="h-4" />
 
-                    <div class="flex items-center">
-                        <label class=" font-thin8 text-sm" for="name">
-                            "Name: "
+                    <ProfileFields profile />
+
+                    <div class="flex items-center gap-2 mt-2">
+                        <label class=" font-thin8 text-sm" for="max-players">
+                            "Players: "
                         </label>
                         <input
-                            class="bg-white/10 focus:outline-white/50  text-md font-thin8 p-2"
-                            name="name"
+                            class="bg-white/10 focus:outline-white/50 text-md p-2 w-16"
+                            name="max-players"
+                            type="number"
+                            min=1
+                            max=MAX_PLAYERS
+                            prop:value=move || options.with(|o| o.limits.max_players)
+                            on:input=move |ev| {
+                                if let Ok(max_players) = event_target_value(&ev).parse() {
+                                    options.update(|o| o.limits.max_players = max_players);
+                                }
+                            }
+                        />
+                        <label class=" font-thin8 text-sm" for="max-spectators">
+                            "Spectators: "
+                        </label>
+                        <input
+                            class="bg-white/10 focus:outline-white/50 text-md p-2 w-16"
+                            name="max-spectators"
+                            type="number"
+                            min=0
+                            placeholder="∞"
+                            on:input=move |ev| {
+                                // Left blank for no limit
+                                let max_spectators = event_target_value(&ev).parse().ok();
+                                options.update(|o| o.limits.max_spectators = max_spectators);
+                            }
+                        />
+                    </div>
+
+                    <div class="flex items-center gap-2 mt-2">
+                        <label class=" font-thin8 text-sm" for="public">
+                            "Public: "
+                        </label>
+                        <input
+                            name="public"
+                            type="checkbox"
+                            prop:checked=move || options.with(|o| o.public_title.is_some())
+                            on:change=move |ev| {
+                                let public = event_target_checked(&ev);
+                                options.update(|o| o.public_title = public.then(String::new));
+                            }
+                        />
+                        <input
+                            class="bg-white/10 focus:outline-white/50 text-md font-thin8 p-2 flex-1"
+                            class=("hidden", move || options.with(|o| o.public_title.is_none()))
+                            name="title"
                             type="text"
-                            placeholder="Enter your name"
+                            placeholder="Room title"
+                            maxlength=MAX_TITLE_LEN
+                            prop:value=move || options.with(|o| o.public_title.clone().unwrap_or_default())
                             on:input=move |ev| {
-                                set_name.set(event_target_value(&ev));
+                                options.update(|o| o.public_title = Some(event_target_value(&ev)));
                             }
                         />
                     </div>
 
+                    <div class="flex items-center gap-2 mt-2">
+                        <label class=" font-thin8 text-sm" for="room-code">
+                            "Room code: "
+                        </label>
+                        <input
+                            class="bg-white/10 focus:outline-white/50 text-md font-thin8 p-2 flex-1"
+                            name="room-code"
+                            type="text"
+                            placeholder="Random"
+                            maxlength=MAX_ROOM_ID_LEN
+                            on:input=move |ev| {
+                                // Left blank for a generated code
+                                let code = event_target_value(&ev);
+                                options
+                                    .update(|o| o.room_code = (!code.trim().is_empty()).then_some(code));
+                            }
+                        />
+                    </div>
+
+                    <DialogErrors errors />
+
                     <div class="h-4" />
 
                     <button
                         class="text-sm hover:bg-white/20 self-center px-4 py-1"
                         type="button"
                         on:click=move |_| {
-                            if name.get_untracked().is_empty() {
-                                warn!("Name cant be empty");
-                            } else {
-                                let room_manager = expect_context::<RoomManager>();
-                                if let Err(err) = room_manager.host_join(name.get_untracked(), None)
-                                {
-                                    warn!("Cannot join {err:#?}");
-                                }
-                            }
+                            set_errors
+                                .set(
+                                    connect(
+                                        profile.get_untracked().normalized(),
+                                        RoomTarget::Host(options.get_untracked()),
+                                        set_stored_profile,
+                                    ),
+                                );
                         }
                     >
                         "[ Create Room ]"
@@ -70,27 +186,16 @@ pub fn HomePage() -> impl IntoView {
             }
         >
             {
-                let (name, set_name) = create_signal(String::new());
-                let (room_code, set_room_code) = create_signal(String::new());
+                let profile = create_rw_signal(Profile::default());
+                // Prefill with the profile used last time
+                create_effect(move |_| profile.set(stored_profile.get()));
+                let (errors, set_errors) = create_signal(Vec::new());
                 view! {
                     <h3 class="font-bold2  text-xl text-center w-full">"Join"</h3>
 
                     <div class="h-4" />
 
-                    <div class="flex items-center">
-                        <label class=" font-thin8 text-sm" for="name">
-                            "Name: "
-                        </label>
-                        <input
-                            class="bg-white/10 focus:outline-white/50  text-md font-thin8 p-2"
-                            name="name"
-                            type="text"
-                            placeholder="Enter your name"
-                            on:input=move |ev| {
-                                set_name.set(event_target_value(&ev));
-                            }
-                        />
-                    </div>
+                    <ProfileFields profile />
 
                     <div class="flex items-center">
                         <label class=" font-thin8 text-sm" for="roomid">
@@ -101,33 +206,29 @@ pub fn HomePage() -> impl IntoView {
                             name="roomid"
                             type="text"
                             placeholder="Room Id"
+                            prop:value=room_code
                             on:input=move |ev| {
                                 set_room_code.set(event_target_value(&ev));
                             }
                         />
                     </div>
 
+                    <DialogErrors errors />
+
                     <div class="h-4" />
 
                     <button
                         class="text-sm hover:bg-white/20 self-center px-4 py-1"
                         type="button"
                         on:click=move |_| {
-                            if name.get_untracked().is_empty()
-                                || room_code.get_untracked().is_empty()
-                            {
-                                warn!("Name cant be empty");
-                            } else {
-                                let room_manager = expect_context::<RoomManager>();
-                                if let Err(err) = room_manager
-                                    .host_join(
-                                        name.get_untracked(),
-                                        Some(room_code.get_untracked()),
-                                    )
-                                {
-                                    warn!("Cannot join {err:#?}");
-                                }
-                            }
+                            set_errors
+                                .set(
+                                    connect(
+                                        profile.get_untracked().normalized(),
+                                        RoomTarget::Join(room_code.get_untracked()),
+                                        set_stored_profile,
+                                    ),
+                                );
                         }
                     >
                         "[ Join Room ]"
@@ -151,7 +252,11 @@ pub fn HomePage() -> impl IntoView {
                     "[ Join ]"
                 </button>
             </div>
-
+            <div class="h-8" />
+            <RoomList on_join=move |room_id| {
+                set_room_code.set(room_id);
+                set_join_open.set(true);
+            } />
         </div>
     }
 }
@@ -4,14 +4,54 @@ use leptos_router::*;
 use wasm_bindgen::{JsCast, UnwrapThrowExt};
 use web_sys::{js_sys::Uint8Array, Blob};
 
+use common::Profile;
+
 use crate::{
     components::{
-        chatbox::ChatBox, player::Player, room_info::RoomInfo, video_player::VideoPlayer,
-        virtual_buttons::VirtualButtons,
+        chatbox::ChatBox, player::Player, profile::ProfileFields, room_info::RoomInfo,
+        video_player::VideoPlayer, virtual_buttons::VirtualButtons,
     },
-    networking::room_manager::RoomManager,
+    networking::room_manager::{RoomManager, RoomTarget},
+    pages::home_page::{connect, DialogErrors},
+    utils::profile::use_stored_profile,
 };
 
+/// Shown when opening an invite link, joins `room_id` once the user picked
+/// a name.
+#[component]
+fn JoinPrompt(room_id: String) -> impl IntoView {
+    let (stored_profile, set_stored_profile) = use_stored_profile();
+    let profile = create_rw_signal(Profile::default());
+    // Prefill with the profile used last time
+    create_effect(move |_| profile.set(stored_profile.get()));
+    let (errors, set_errors) = create_signal(Vec::new());
+    let heading = format!("Join room {}", room_id.to_uppercase());
+    view! {
+        <div class="h-full w-full flex flex-col items-center justify-center">
+            <h1 class="text-xl font-bold2">{heading}</h1>
+            <div class="h-4" />
+            <ProfileFields profile />
+            <DialogErrors errors />
+            <div class="h-4" />
+            <button
+                class="text-sm hover:bg-white/20 px-4 py-1"
+                on:click=move |_| {
+                    set_errors
+                        .set(
+                            connect(
+                                profile.get_untracked().normalized(),
+                                RoomTarget::Join(room_id.clone()),
+                                set_stored_profile,
+                            ),
+                        );
+                }
+            >
+                "[ Join Room ]"
+            </button>
+        </div>
+    }
+}
+
 #[derive(Params, PartialEq, Clone)]
 struct RoomParam {
     id: Option<String>,
@@ -33,16 +73,44 @@ pub fn RoomPage() -> impl IntoView {
 
     let (keyevent_rx, keyevent_tx) = create_signal(None);
     let room_info = room_manager.get_room_info();
+    let mentioned = room_manager.get_mentioned();
+    // Only swap the player when the user's part in the room changes
+    let role =
+        create_memo(move |_| room_info.with(|r| r.as_ref().map(|r| (r.is_host, r.own_role()))));
+    // Spectators ahead of the user for a player spot
+    let queue_position = move || {
+        room_info.with(|r| {
+            r.as_ref()
+                .map(|r| {
+                    r.users
+                        .iter()
+                        .filter(|u| u.role.is_spectator())
+                        .take_while(|u| u.id != r.user_id)
+                        .count()
+                })
+                .unwrap_or_default()
+        })
+    };
 
     view! {
         {move || {
             if let Ok(RoomParam { id: Some(room_id) }) = params.get() {
                 if !room_id.is_empty() {
+                    let join_room_id = room_id.clone();
                     view! {
-                        <Title text=format!("Room {room_id}") />
+                        <Title text={
+                            let room_id = room_id.clone();
+                            // Flash the tab when mentioned in chat
+                            move || if mentioned.get() {
+                                format!("@ Room {room_id}")
+                            } else {
+                                format!("Room {room_id}")
+                            }
+                        } />
                         {
                             move || {
                                 if is_csr.get(){
+                                    let join_room_id = join_room_id.clone();
                                     view! {
                                         <RoomInfo />
                                         <ChatBox />
@@ -50,8 +118,22 @@ pub fn RoomPage() -> impl IntoView {
 
                                         {
                                             move || {
-                                                if let Some(room_info) = room_info.get(){
-                                                    if !room_info.is_host {
+                                                if let Some((is_host, role)) = role.get(){
+                                                    if role.is_spectator() {
+                                                        view! {
+                                                            <div class="h-full w-full flex flex-col items-center justify-center">
+                                                                <h1 class="text-xl font-bold2">"The room is full"</h1>
+                                                                <div class="h-4" />
+                                                                <div>"You're spectating, you'll join as soon as a player leaves"</div>
+                                                                <div class="text-sm">
+                                                                    {move || match queue_position() {
+                                                                        0 => "You're next in line".to_string(),
+                                                                        ahead => format!("{ahead} ahead of you"),
+                                                                    }}
+                                                                </div>
+                                                            </div>
+                                                        }.into_view()
+                                                    } else if !is_host {
                                                         view! {
                                                             <VideoPlayer
                                                                 events_rx=keyevent_rx
@@ -64,7 +146,10 @@ pub fn RoomPage() -> impl IntoView {
                                                         }.into_view()
                                                     }
                                                 }else{
-                                                    view! {}.into_view()
+                                                    // Opened from an invite link
+                                                    view! {
+                                                        <JoinPrompt room_id=join_room_id.clone() />
+                                                    }.into_view()
                                                 }
                                             }
                                         }
@@ -79,7 +164,7 @@ pub fn RoomPage() -> impl IntoView {
                             move || {
                                 let room_info = room_info.get().map(|r|r.is_host);
 
-                                if room_info != Some(false){
+                                if room_info == Some(true){
                                     view! {
                                         <div
                                             class="h-full w-full flex px-10 py-4 items-center justify-center flex-col"
@@ -0,0 +1,261 @@
+//! Chat input parsing. Slash commands map to their own [`ClientMessage`],
+//! the server validates them like any other message.
+
+use common::{
+    message::{ClientMessage, DiceRoll, ServerError},
+    Profile, UserMeta, MAX_SLOTS,
+};
+use uuid::Uuid;
+
+pub const COMMANDS_HELP: &str =
+    "Commands: /me <action>, /roll [count]d<sides>, /kick <name>, /rename <name>, /slot <1-4|off>";
+
+/// Emotes shown as images when written as `:name:`.
+pub const EMOTES: &[&str] = &[
+    "smile", "sad", "lol", "wow", "heart", "fire", "gg", "thumbsup",
+];
+
+/// Message to send for what `own_id` typed in chat, `//` sends a literal `/`.
+pub fn parse_chat(input: &str, users: &[UserMeta], own_id: Uuid) -> Result<ClientMessage, String> {
+    let input = input.trim();
+    if let Some(text) = input.strip_prefix("//") {
+        return Ok(ClientMessage::Chat(format!("/{text}")));
+    }
+    let Some(command) = input.strip_prefix('/') else {
+        return Ok(ClientMessage::Chat(input.to_string()));
+    };
+    let (command, args) = command
+        .split_once(char::is_whitespace)
+        .map(|(command, args)| (command, args.trim()))
+        .unwrap_or((command, ""));
+    match command.to_lowercase().as_str() {
+        "me" if !args.is_empty() => Ok(ClientMessage::Action(args.to_string())),
+        "roll" => parse_roll(if args.is_empty() { "d6" } else { args })
+            .map(ClientMessage::Roll)
+            .ok_or_else(|| ServerError::InvalidRoll.to_string()),
+        "kick" => {
+            let name = args.trim_start_matches('@');
+            // Names with spaces can also be written like a mention
+            users
+                .iter()
+                .find(|u| u.name.eq_ignore_ascii_case(name))
+                .or_else(|| users.iter().find(|u| is_mention_of(name, &u.name)))
+                .map(|u| ClientMessage::Kick(u.id))
+                .ok_or_else(|| format!("No user named {name}"))
+        }
+        "rename" if !args.is_empty() => users
+            .iter()
+            .find(|u| u.id == own_id)
+            .map(|own| {
+                ClientMessage::UpdateProfile(Profile {
+                    name: args.to_string(),
+                    ..own.profile()
+                })
+            })
+            .ok_or_else(|| "Not in a room".to_string()),
+        "slot" => match args {
+            "" | "off" => Ok(ClientMessage::ClaimSlot(None)),
+            slot => slot
+                .parse()
+                .ok()
+                .filter(|slot| (1..=MAX_SLOTS).contains(slot))
+                .map(|slot| ClientMessage::ClaimSlot(Some(slot)))
+                .ok_or_else(|| ServerError::InvalidSlot { max: MAX_SLOTS }.to_string()),
+        },
+        _ => Err(COMMANDS_HELP.to_string()),
+    }
+}
+
+/// `d20`, `2d6` or just `20`.
+fn parse_roll(args: &str) -> Option<DiceRoll> {
+    let args = args.to_lowercase();
+    let (count, sides) = match args.split_once('d') {
+        Some(("", sides)) => (1, sides.parse().ok()?),
+        Some((count, sides)) => (count.parse().ok()?, sides.parse().ok()?),
+        None => (1, args.parse().ok()?),
+    };
+    let roll = DiceRoll { count, sides };
+    roll.is_valid().then_some(roll)
+}
+
+/// Piece of a chat message as it's drawn.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatToken<'a> {
+    Text(&'a str),
+    Emote(&'static str),
+    /// `@name`, without the `@`
+    Mention(&'a str),
+}
+
+pub fn tokenize(text: &str) -> Vec<ChatToken<'_>> {
+    text.split_inclusive(char::is_whitespace)
+        .flat_map(|piece| {
+            let word = piece.trim_end();
+            let space = &piece[word.len()..];
+            let token = if let Some(emote) = word
+                .strip_prefix(':')
+                .and_then(|w| w.strip_suffix(':'))
+                .and_then(|name| EMOTES.iter().find(|e| **e == name))
+            {
+                ChatToken::Emote(emote)
+            } else if let Some(name) = word.strip_prefix('@').filter(|n| !n.is_empty()) {
+                ChatToken::Mention(name)
+            } else {
+                ChatToken::Text(word)
+            };
+            [token, ChatToken::Text(space)]
+        })
+        .filter(|token| *token != ChatToken::Text(""))
+        .collect()
+}
+
+/// Whether `mention` refers to the user called `name`, spaces in names are
+/// left out when mentioning.
+pub fn is_mention_of(mention: &str, name: &str) -> bool {
+    let mention = mention.trim_end_matches(|c: char| !c.is_alphanumeric());
+    let name = name.split_whitespace().collect::<String>();
+    !name.is_empty() && mention.eq_ignore_ascii_case(&name)
+}
+
+pub fn mentions(text: &str, name: &str) -> bool {
+    tokenize(text)
+        .into_iter()
+        .any(|token| matches!(token, ChatToken::Mention(mention) if is_mention_of(mention, name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use common::{UserRole, UserState};
+
+    use super::*;
+
+    fn user(name: &str) -> UserMeta {
+        UserMeta {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            state: UserState::VideoNotSelected,
+            latency: None,
+            slot: None,
+            color: None,
+            emoji: None,
+            role: UserRole::Player,
+        }
+    }
+
+    #[test]
+    fn roll_bounds() {
+        assert_eq!(
+            parse_roll("d20"),
+            Some(DiceRoll {
+                count: 1,
+                sides: 20
+            })
+        );
+        assert_eq!(parse_roll("2D6"), Some(DiceRoll { count: 2, sides: 6 }));
+        assert_eq!(
+            parse_roll("100"),
+            Some(DiceRoll {
+                count: 1,
+                sides: 100
+            })
+        );
+        assert_eq!(
+            parse_roll("10d1000"),
+            Some(DiceRoll {
+                count: DiceRoll::MAX_COUNT,
+                sides: DiceRoll::MAX_SIDES
+            })
+        );
+        assert_eq!(parse_roll("11d6"), None);
+        assert_eq!(parse_roll("d1001"), None);
+        assert_eq!(parse_roll("0d6"), None);
+        assert_eq!(parse_roll("d1"), None);
+        assert_eq!(parse_roll("-2d6"), None);
+        assert_eq!(parse_roll("two"), None);
+    }
+
+    #[test]
+    fn roll_command() {
+        let own = user("me");
+        assert!(matches!(
+            parse_chat("/roll", &[], own.id),
+            Ok(ClientMessage::Roll(DiceRoll { count: 1, sides: 6 }))
+        ));
+        assert!(matches!(
+            parse_chat("/ROLL 3d8", &[], own.id),
+            Ok(ClientMessage::Roll(DiceRoll { count: 3, sides: 8 }))
+        ));
+        assert_eq!(
+            parse_chat("/roll 99d6", &[], own.id).err(),
+            Some(ServerError::InvalidRoll.to_string())
+        );
+    }
+
+    #[test]
+    fn kick_resolves_names() {
+        let own = user("me");
+        let bob = user("Big Bob");
+        let users = [own.clone(), bob.clone()];
+        for input in ["/kick big bob", "/kick @Big Bob", "/kick @BigBob"] {
+            assert!(
+                matches!(parse_chat(input, &users, own.id), Ok(ClientMessage::Kick(id)) if id == bob.id),
+                "{input}"
+            );
+        }
+        assert_eq!(
+            parse_chat("/kick @nobody", &users, own.id).err(),
+            Some("No user named nobody".to_string())
+        );
+    }
+
+    #[test]
+    fn plain_and_escaped_text() {
+        let own = user("me");
+        assert!(matches!(
+            parse_chat("  hello  ", &[], own.id),
+            Ok(ClientMessage::Chat(text)) if text == "hello"
+        ));
+        assert!(matches!(
+            parse_chat("//roll", &[], own.id),
+            Ok(ClientMessage::Chat(text)) if text == "/roll"
+        ));
+        assert!(matches!(
+            parse_chat("/me waves", &[], own.id),
+            Ok(ClientMessage::Action(text)) if text == "waves"
+        ));
+    }
+
+    #[test]
+    fn unknown_or_incomplete_commands_show_help() {
+        let users = [user("me")];
+        for input in ["/dance", "/me", "/rename", "/"] {
+            assert_eq!(
+                parse_chat(input, &users, users[0].id).err(),
+                Some(COMMANDS_HELP.to_string()),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn rename_and_slot() {
+        let own = user("me");
+        assert!(matches!(
+            parse_chat("/rename New Name", std::slice::from_ref(&own), own.id),
+            Ok(ClientMessage::UpdateProfile(profile)) if profile.name == "New Name"
+        ));
+        assert_eq!(
+            parse_chat("/rename New Name", &[], own.id).err(),
+            Some("Not in a room".to_string())
+        );
+        assert!(matches!(
+            parse_chat("/slot 2", &[], own.id),
+            Ok(ClientMessage::ClaimSlot(Some(2)))
+        ));
+        assert!(matches!(
+            parse_chat("/slot off", &[], own.id),
+            Ok(ClientMessage::ClaimSlot(None))
+        ));
+        assert!(parse_chat("/slot 5", &[], own.id).is_err());
+    }
+}
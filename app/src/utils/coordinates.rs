@@ -0,0 +1,319 @@
+//! Mapping pointer positions between the page, the game and ruffle.
+//!
+//! Mouse positions travel as fractions of the game's width and height, so
+//! they mean the same on every screen regardless of its size or device pixel
+//! ratio. The host's canvas is exactly ruffle's viewport, the guest's
+//! `<video>` shows that viewport letterboxed by `object-fit: contain`. Ruffle
+//! maps viewport pixels to its `ShowAll` stage itself, [`fit_contain`] gives
+//! the same stage area for drawing on top of the game.
+
+use web_sys::{Element, HtmlVideoElement};
+
+use crate::utils::keycode::KeyEvent;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Size {
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    pub fn from_dom(rect: &web_sys::DomRect) -> Self {
+        Self {
+            left: rect.left(),
+            top: rect.top(),
+            width: rect.width(),
+            height: rect.height(),
+        }
+    }
+}
+
+/// Area `content` covers when scaled to fit inside `container` keeping its
+/// aspect ratio and centered, like `object-fit: contain` and ruffle's
+/// `ShowAll` scale mode. Empty content fills the container.
+pub fn fit_contain(container: Rect, content: Size) -> Rect {
+    if content.width <= 0.0 || content.height <= 0.0 {
+        return container;
+    }
+    let scale = (container.width / content.width).min(container.height / content.height);
+    let (width, height) = (content.width * scale, content.height * scale);
+    Rect {
+        left: container.left + (container.width - width) / 2.0,
+        top: container.top + (container.height - height) / 2.0,
+        width,
+        height,
+    }
+}
+
+/// `point` as a fraction of `area`, `None` when it is outside.
+pub fn normalize(point: (f64, f64), area: Rect) -> Option<(f64, f64)> {
+    if area.width <= 0.0 || area.height <= 0.0 {
+        return None;
+    }
+    let x = (point.0 - area.left) / area.width;
+    let y = (point.1 - area.top) / area.height;
+    ((0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y)).then_some((x, y))
+}
+
+/// `point` as a fraction of `area`, points outside are moved to its edge.
+pub fn normalize_clamped(point: (f64, f64), area: Rect) -> (f64, f64) {
+    let clamp = |value: f64| {
+        if value.is_finite() {
+            value.clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    };
+    (
+        clamp((point.0 - area.left) / area.width),
+        clamp((point.1 - area.top) / area.height),
+    )
+}
+
+/// Physical pixel of ruffle's viewport at a normalized position, `viewport`
+/// is the canvas size in CSS pixels.
+pub fn to_viewport(position: (f64, f64), viewport: Size, device_pixel_ratio: f64) -> (f64, f64) {
+    (
+        position.0 * viewport.width * device_pixel_ratio,
+        position.1 * viewport.height * device_pixel_ratio,
+    )
+}
+
+/// Normalized area of a viewport the `stage` is shown in, ruffle's `ShowAll`.
+pub fn stage_area(viewport: Size, stage: Size) -> Rect {
+    let area = fit_contain(
+        Rect {
+            left: 0.0,
+            top: 0.0,
+            width: viewport.width,
+            height: viewport.height,
+        },
+        stage,
+    );
+    Rect {
+        left: area.left / viewport.width,
+        top: area.top / viewport.height,
+        width: area.width / viewport.width,
+        height: area.height / viewport.height,
+    }
+}
+
+/// Mouse position kept by the host, relative movement from a locked pointer
+/// moves it around.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VirtualCursor {
+    pub position: (f64, f64),
+    /// Whether the last movement was relative, the cursor is only drawn then
+    pub is_relative: bool,
+}
+
+impl Default for VirtualCursor {
+    fn default() -> Self {
+        Self {
+            position: (0.5, 0.5),
+            is_relative: false,
+        }
+    }
+}
+
+impl VirtualCursor {
+    /// Absolute version of `event`, relative movement is kept inside `stage`.
+    pub fn resolve(&mut self, event: KeyEvent, stage: Rect) -> KeyEvent {
+        match event {
+            KeyEvent::MouseMove(x, y) => {
+                self.position = (x, y);
+                self.is_relative = false;
+                event
+            }
+            KeyEvent::MouseDown(x, y, _) | KeyEvent::MouseUp(x, y, _) => {
+                self.position = (x, y);
+                event
+            }
+            KeyEvent::MouseMoveBy(dx, dy) => {
+                let (x, y) = self.position;
+                self.position = (
+                    (x + dx).clamp(stage.left, stage.left + stage.width),
+                    (y + dy).clamp(stage.top, stage.top + stage.height),
+                );
+                self.is_relative = true;
+                KeyEvent::MouseMove(self.position.0, self.position.1)
+            }
+            KeyEvent::CursorDown(button) => {
+                KeyEvent::MouseDown(self.position.0, self.position.1, button)
+            }
+            KeyEvent::CursorUp(button) => {
+                KeyEvent::MouseUp(self.position.0, self.position.1, button)
+            }
+            event => event,
+        }
+    }
+}
+
+/// Client position of a mouse event.
+pub fn client_position(ev: &web_sys::MouseEvent) -> (f64, f64) {
+    (f64::from(ev.client_x()), f64::from(ev.client_y()))
+}
+
+/// Area of the page showing the game on the host's canvas.
+pub fn canvas_area(canvas: &Element) -> Rect {
+    Rect::from_dom(&canvas.get_bounding_client_rect())
+}
+
+/// Area of the page showing the stream, without the letterbox bars.
+pub fn video_area(video: &HtmlVideoElement) -> Rect {
+    fit_contain(
+        Rect::from_dom(&video.get_bounding_client_rect()),
+        Size {
+            width: f64::from(video.video_width()),
+            height: f64::from(video.video_height()),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: f64, top: f64, width: f64, height: f64) -> Rect {
+        Rect {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    fn size(width: f64, height: f64) -> Size {
+        Size { width, height }
+    }
+
+    fn assert_close(actual: (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-9 && (actual.1 - expected.1).abs() < 1e-9,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn fit_contain_same_aspect_fills() {
+        let container = rect(10.0, 20.0, 800.0, 600.0);
+        assert_eq!(fit_contain(container, size(400.0, 300.0)), container);
+    }
+
+    #[test]
+    fn fit_contain_wide_content_letterboxes() {
+        // 16:9 stream in a portrait phone
+        let area = fit_contain(rect(0.0, 0.0, 390.0, 844.0), size(1280.0, 720.0));
+        assert_eq!(area.width, 390.0);
+        assert!((area.height - 219.375).abs() < 1e-9);
+        assert!((area.top - (844.0 - 219.375) / 2.0).abs() < 1e-9);
+        assert_eq!(area.left, 0.0);
+    }
+
+    #[test]
+    fn fit_contain_tall_content_pillarboxes() {
+        // 4:3 game on a 16:9 monitor
+        let area = fit_contain(rect(0.0, 50.0, 1920.0, 1080.0), size(800.0, 600.0));
+        assert_eq!(area, rect(240.0, 50.0, 1440.0, 1080.0));
+    }
+
+    #[test]
+    fn fit_contain_without_content_size() {
+        let container = rect(0.0, 0.0, 300.0, 200.0);
+        assert_eq!(fit_contain(container, size(0.0, 0.0)), container);
+    }
+
+    #[test]
+    fn normalize_inside() {
+        let area = rect(100.0, 50.0, 400.0, 200.0);
+        assert_close(normalize((300.0, 150.0), area).unwrap(), (0.5, 0.5));
+        assert_close(normalize((100.0, 50.0), area).unwrap(), (0.0, 0.0));
+        assert_close(normalize((500.0, 250.0), area).unwrap(), (1.0, 1.0));
+    }
+
+    #[test]
+    fn normalize_outside() {
+        let area = rect(100.0, 50.0, 400.0, 200.0);
+        assert_eq!(normalize((99.0, 150.0), area), None);
+        assert_eq!(normalize((300.0, 251.0), area), None);
+        assert_eq!(normalize((0.0, 0.0), rect(0.0, 0.0, 0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn normalize_clamped_moves_to_edge() {
+        let area = rect(100.0, 50.0, 400.0, 200.0);
+        assert_close(normalize_clamped((0.0, 400.0), area), (0.0, 1.0));
+        assert_close(normalize_clamped((300.0, 100.0), area), (0.5, 0.25));
+        assert_close(
+            normalize_clamped((1.0, 1.0), rect(0.0, 0.0, 0.0, 0.0)),
+            (0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn letterboxed_click_maps_to_stage() {
+        // Phone guest taps the middle of the letterboxed stream
+        let video = fit_contain(rect(0.0, 0.0, 390.0, 844.0), size(1280.0, 720.0));
+        assert_eq!(normalize((195.0, 100.0), video), None);
+        let position = normalize((195.0, 422.0), video).unwrap();
+        assert_close(position, (0.5, 0.5));
+
+        // Host canvas is 640x360 CSS pixels on a 2x screen
+        assert_close(
+            to_viewport(position, size(640.0, 360.0), 2.0),
+            (640.0, 360.0),
+        );
+    }
+
+    #[test]
+    fn stage_area_of_show_all() {
+        // 4:3 stage in a 16:9 viewport is pillarboxed
+        let stage = stage_area(size(1600.0, 900.0), size(800.0, 600.0));
+        assert_close((stage.left, stage.top), (0.125, 0.0));
+        assert_close((stage.width, stage.height), (0.75, 1.0));
+    }
+
+    #[test]
+    fn virtual_cursor_stays_on_stage() {
+        use crate::utils::keycode::MouseButton;
+
+        let stage = rect(0.125, 0.0, 0.75, 1.0);
+        let mut cursor = VirtualCursor::default();
+        assert_eq!(
+            cursor.resolve(KeyEvent::MouseMoveBy(-1.0, 0.25), stage),
+            KeyEvent::MouseMove(0.125, 0.75)
+        );
+        assert!(cursor.is_relative);
+        assert_eq!(
+            cursor.resolve(KeyEvent::CursorDown(MouseButton::Left), stage),
+            KeyEvent::MouseDown(0.125, 0.75, MouseButton::Left)
+        );
+
+        cursor.resolve(KeyEvent::MouseMove(0.5, 0.5), stage);
+        assert!(!cursor.is_relative);
+        assert_eq!(
+            cursor.resolve(KeyEvent::MouseMoveBy(0.1, 0.0), stage),
+            KeyEvent::MouseMove(0.6, 0.5)
+        );
+    }
+
+    #[test]
+    fn dpr_does_not_leak_between_screens() {
+        // Same spot on a 3x guest and a 1x host
+        let guest = rect(0.0, 0.0, 300.0, 150.0);
+        let position = normalize((75.0, 150.0), guest).unwrap();
+        assert_close(position, (0.25, 1.0));
+        assert_close(
+            to_viewport(position, size(1200.0, 600.0), 1.0),
+            (300.0, 600.0),
+        );
+    }
+}
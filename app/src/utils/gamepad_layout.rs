@@ -0,0 +1,184 @@
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use leptos::window;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::utils::keycode::{Key, KeyEvent};
+
+/// Saved state of the virtual gamepad, positions are in percent of the
+/// screen and translations in pixels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GamepadLayout {
+    pub buttons: Vec<ButtonLayout>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ButtonLayout {
+    pub position: (f32, f32),
+    pub translation: (f32, f32),
+    pub width: f32,
+    pub scale: f32,
+    pub kind: ButtonKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ButtonKind {
+    Dpad((Key, Key, Key, Key)),
+    Button(Key),
+    Joystick((Key, Key, Key, Key)),
+    Trackpad,
+    Toggle(Key),
+    /// Key and how many times a second it is pressed
+    Turbo(Key, u32),
+    Macro(Vec<MacroStep>),
+}
+
+/// Default presses per second of a turbo button.
+pub const DEFAULT_TURBO_RATE: u32 = 10;
+
+/// One recorded event of a macro button.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MacroStep {
+    /// Time since the previous step
+    pub delay_ms: u32,
+    pub event: KeyEvent,
+}
+
+impl ButtonLayout {
+    /// Button of the given kind in the middle of the screen.
+    pub fn new(kind: ButtonKind) -> Self {
+        let width = match &kind {
+            ButtonKind::Dpad(_) => 20.0,
+            ButtonKind::Joystick(_) => 15.0,
+            ButtonKind::Trackpad => 25.0,
+            ButtonKind::Button(_)
+            | ButtonKind::Toggle(_)
+            | ButtonKind::Turbo(..)
+            | ButtonKind::Macro(_) => 5.0,
+        };
+        Self {
+            position: (50.0 - width / 2.0, 40.0),
+            translation: (0.0, 0.0),
+            width,
+            scale: 1.0,
+            kind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+impl Orientation {
+    pub fn from_size(width: f64, height: f64) -> Self {
+        if width > height {
+            Orientation::Landscape
+        } else {
+            Orientation::Portrait
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Orientation::Portrait => "portrait",
+            Orientation::Landscape => "landscape",
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum LayoutCodeError {
+    #[error("layout code is not valid base64")]
+    Base64(#[from] base64::DecodeError),
+    #[error("layout code is not a valid layout")]
+    Json(#[from] serde_json::Error),
+}
+
+impl GamepadLayout {
+    /// Shareable text form of the layout.
+    pub fn to_code(&self) -> Result<String, LayoutCodeError> {
+        let json = serde_json::to_vec(self)?;
+        Ok(BASE64_URL_SAFE_NO_PAD.encode(json))
+    }
+
+    pub fn from_code(code: &str) -> Result<Self, LayoutCodeError> {
+        let json = BASE64_URL_SAFE_NO_PAD.decode(code.trim())?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    /// Layout saved for the game in the given orientation, `game` is `None`
+    /// while nothing is selected.
+    pub fn load(game: Option<&str>, orientation: Orientation) -> Option<Self> {
+        let storage = window().local_storage().ok().flatten()?;
+        let code = storage
+            .get_item(&storage_key(game, orientation))
+            .ok()
+            .flatten()?;
+        match Self::from_code(&code) {
+            Ok(layout) => Some(layout),
+            Err(err) => {
+                warn!("Ignoring saved gamepad layout {err:?}");
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, game: Option<&str>, orientation: Orientation) {
+        let code = match self.to_code() {
+            Ok(code) => code,
+            Err(err) => {
+                warn!("Cant encode gamepad layout {err:?}");
+                return;
+            }
+        };
+        match window().local_storage() {
+            Ok(Some(storage)) => {
+                if let Err(err) = storage.set_item(&storage_key(game, orientation), &code) {
+                    warn!("Cant save gamepad layout {err:?}");
+                }
+            }
+            _ => warn!("Local storage not available"),
+        }
+    }
+}
+
+impl Default for GamepadLayout {
+    fn default() -> Self {
+        let button = |position, key| ButtonLayout {
+            position,
+            translation: (0.0, 0.0),
+            width: 5.0,
+            scale: 1.0,
+            kind: ButtonKind::Button(key),
+        };
+        Self {
+            buttons: vec![
+                ButtonLayout {
+                    position: (10.0, 60.0),
+                    translation: (0.0, 0.0),
+                    width: 20.0,
+                    scale: 1.0,
+                    kind: ButtonKind::Dpad((Key::A, Key::D, Key::W, Key::S)),
+                },
+                button((80.0, 70.0), Key::Z),
+                button((90.0, 70.0), Key::X),
+                button((85.0, 60.0), Key::C),
+                button((85.0, 80.0), Key::V),
+                button((75.0, 82.5), Key::Space),
+                button((92.5, 50.0), Key::Enter),
+            ],
+        }
+    }
+}
+
+fn storage_key(game: Option<&str>, orientation: Orientation) -> String {
+    format!(
+        "gamepad_layout:{}:{}",
+        orientation.as_str(),
+        game.unwrap_or_default()
+    )
+}
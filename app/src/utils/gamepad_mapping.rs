@@ -0,0 +1,236 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::keycode::Key;
+
+/// Button indices of the W3C standard gamepad layout.
+pub mod standard {
+    pub const A: u32 = 0;
+    pub const B: u32 = 1;
+    pub const X: u32 = 2;
+    pub const Y: u32 = 3;
+    pub const LEFT_BUMPER: u32 = 4;
+    pub const RIGHT_BUMPER: u32 = 5;
+    pub const SELECT: u32 = 8;
+    pub const START: u32 = 9;
+    pub const DPAD_UP: u32 = 12;
+    pub const DPAD_DOWN: u32 = 13;
+    pub const DPAD_LEFT: u32 = 14;
+    pub const DPAD_RIGHT: u32 = 15;
+
+    pub const LEFT_STICK_X: u32 = 0;
+    pub const LEFT_STICK_Y: u32 = 1;
+}
+
+/// Controller mappings, the n-th connected controller plays in slot n.
+/// Controllers without a slot are ignored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GamepadConfig {
+    pub slots: Vec<ControllerMapping>,
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        Self {
+            slots: vec![
+                ControllerMapping::with_dpad(
+                    (
+                        Key::LeftArrow,
+                        Key::RightArrow,
+                        Key::UpArrow,
+                        Key::DownArrow,
+                    ),
+                    vec![
+                        (standard::A, Key::Space),
+                        (standard::B, Key::Z),
+                        (standard::X, Key::X),
+                        (standard::Y, Key::C),
+                        (standard::SELECT, Key::Escape),
+                        (standard::START, Key::Enter),
+                    ],
+                ),
+                ControllerMapping::with_dpad(
+                    (Key::A, Key::D, Key::W, Key::S),
+                    vec![
+                        (standard::A, Key::J),
+                        (standard::B, Key::K),
+                        (standard::X, Key::L),
+                        (standard::Y, Key::I),
+                    ],
+                ),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ControllerMapping {
+    pub buttons: Vec<ButtonMapping>,
+    pub sticks: Vec<StickMapping>,
+    /// Stick deflection below this is treated as centered
+    pub dead_zone: f64,
+    /// Deflection along an axis past which its direction key is held
+    pub threshold: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ButtonMapping {
+    pub button: u32,
+    pub key: Key,
+}
+
+/// Pair of axes acting as a dpad.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StickMapping {
+    pub x_axis: u32,
+    pub y_axis: u32,
+    /// Left, right, up and down keys, same order as the virtual dpad
+    pub keys: (Key, Key, Key, Key),
+}
+
+impl ControllerMapping {
+    /// Mapping where both the dpad buttons and the left stick press `dpad` keys.
+    pub fn with_dpad(dpad: (Key, Key, Key, Key), buttons: Vec<(u32, Key)>) -> Self {
+        let (left, right, up, down) = dpad;
+        let mut buttons = buttons
+            .into_iter()
+            .map(|(button, key)| ButtonMapping { button, key })
+            .collect::<Vec<_>>();
+        buttons.extend([
+            ButtonMapping {
+                button: standard::DPAD_LEFT,
+                key: left,
+            },
+            ButtonMapping {
+                button: standard::DPAD_RIGHT,
+                key: right,
+            },
+            ButtonMapping {
+                button: standard::DPAD_UP,
+                key: up,
+            },
+            ButtonMapping {
+                button: standard::DPAD_DOWN,
+                key: down,
+            },
+        ]);
+        Self {
+            buttons,
+            sticks: vec![StickMapping {
+                x_axis: standard::LEFT_STICK_X,
+                y_axis: standard::LEFT_STICK_Y,
+                keys: dpad,
+            }],
+            dead_zone: 0.2,
+            threshold: 0.5,
+        }
+    }
+
+    /// Keys held given the pressed state of every button and the value of every axis.
+    pub fn pressed_keys(&self, buttons: &[bool], axes: &[f64]) -> Vec<Key> {
+        let mut keys = self
+            .buttons
+            .iter()
+            .filter(|mapping| buttons.get(mapping.button as usize) == Some(&true))
+            .map(|mapping| mapping.key)
+            .collect::<Vec<_>>();
+
+        for stick in self.sticks.iter() {
+            let x = axes.get(stick.x_axis as usize).copied().unwrap_or_default();
+            let y = axes.get(stick.y_axis as usize).copied().unwrap_or_default();
+            keys.extend(stick_keys(
+                (x, y),
+                stick.keys,
+                self.dead_zone,
+                self.threshold,
+            ));
+        }
+        keys
+    }
+}
+
+/// Direction keys held by a stick at `(x, y)`, each axis in `-1.0..=1.0`
+/// with up and left negative. `keys` are left, right, up and down.
+pub fn stick_keys(
+    (x, y): (f64, f64),
+    keys: (Key, Key, Key, Key),
+    dead_zone: f64,
+    threshold: f64,
+) -> Vec<Key> {
+    let mut pressed = vec![];
+    if x.hypot(y) < dead_zone {
+        return pressed;
+    }
+    let (left, right, up, down) = keys;
+    if x <= -threshold {
+        pressed.push(left);
+    } else if x >= threshold {
+        pressed.push(right);
+    }
+    if y <= -threshold {
+        pressed.push(up);
+    } else if y >= threshold {
+        pressed.push(down);
+    }
+    pressed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARROWS: (Key, Key, Key, Key) = (
+        Key::LeftArrow,
+        Key::RightArrow,
+        Key::UpArrow,
+        Key::DownArrow,
+    );
+
+    fn keys_at(position: (f64, f64)) -> Vec<Key> {
+        stick_keys(position, ARROWS, 0.2, 0.5)
+    }
+
+    #[test]
+    fn stick_inside_dead_zone_holds_nothing() {
+        assert!(keys_at((0.0, 0.0)).is_empty());
+        assert!(keys_at((0.1, -0.1)).is_empty());
+    }
+
+    #[test]
+    fn stick_below_threshold_holds_nothing() {
+        // Out of the dead zone but not far enough along either axis
+        assert!(keys_at((0.4, 0.4)).is_empty());
+    }
+
+    #[test]
+    fn stick_past_threshold_holds_direction() {
+        assert_eq!(keys_at((-0.8, 0.1)), vec![Key::LeftArrow]);
+        assert_eq!(keys_at((0.5, 0.0)), vec![Key::RightArrow]);
+        assert_eq!(keys_at((0.0, -1.0)), vec![Key::UpArrow]);
+    }
+
+    #[test]
+    fn stick_diagonal_holds_both_keys() {
+        assert_eq!(keys_at((0.7, 0.7)), vec![Key::RightArrow, Key::DownArrow]);
+        assert_eq!(keys_at((-0.6, -0.6)), vec![Key::LeftArrow, Key::UpArrow]);
+    }
+
+    #[test]
+    fn pressed_keys_combines_buttons_and_stick() {
+        let mapping = ControllerMapping::with_dpad(ARROWS, vec![(standard::A, Key::Space)]);
+        let mut buttons = vec![false; 16];
+        buttons[standard::A as usize] = true;
+        buttons[standard::DPAD_UP as usize] = true;
+        let axes = [0.9, 0.0];
+
+        assert_eq!(
+            mapping.pressed_keys(&buttons, &axes),
+            vec![Key::Space, Key::UpArrow, Key::RightArrow]
+        );
+    }
+
+    #[test]
+    fn pressed_keys_ignores_missing_buttons_and_axes() {
+        let mapping = ControllerMapping::with_dpad(ARROWS, vec![(standard::START, Key::Enter)]);
+        assert!(mapping.pressed_keys(&[true], &[]).is_empty());
+    }
+}
@@ -0,0 +1,36 @@
+//! Links and QR codes that bring people straight into a room.
+
+use ::svg::node::{element::path::Data, Value};
+use leptos::window;
+use qrcode::{Color, QrCode};
+
+/// Link to `room_id` on the current site, opening it asks for a name and
+/// joins right away.
+pub fn invite_link(room_id: &str) -> String {
+    let origin = window().location().origin().unwrap_or_default();
+    format!("{origin}/room/{room_id}")
+}
+
+/// SVG path of the dark modules of a QR code for `text`, along with the
+/// code's width in modules. Every module is a 1x1 square.
+pub fn qr_path(text: &str) -> Option<(usize, String)> {
+    let code = QrCode::new(text.as_bytes()).ok()?;
+    let width = code.width();
+    let data = code
+        .to_colors()
+        .chunks(width)
+        .enumerate()
+        .fold(Data::new(), |data, (y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, color)| **color == Color::Dark)
+                .fold(data, |data, (x, _)| {
+                    data.move_to((x as f32, y as f32))
+                        .horizontal_line_by(1.0)
+                        .vertical_line_by(1.0)
+                        .horizontal_line_by(-1.0)
+                        .close()
+                })
+        });
+    Some((width, Value::from(data).to_string()))
+}
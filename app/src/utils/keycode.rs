@@ -41,15 +41,182 @@ pub enum Key {
     CtrlRight,
 
     Enter,
+
+    Digit0,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+
+    ShiftLeft,
+    ShiftRight,
+    AltLeft,
+    AltRight,
+    MetaLeft,
+    MetaRight,
+
+    Tab,
+    Escape,
+    Backspace,
+    CapsLock,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Pause,
+    NumLock,
+    ScrollLock,
+
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+
+    Semicolon,
+    Equal,
+    Comma,
+    Minus,
+    Period,
+    Slash,
+    Backquote,
+    BracketLeft,
+    Backslash,
+    BracketRight,
+    Quote,
+
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadMultiply,
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadDecimal,
+    NumpadDivide,
+    NumpadEnter,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum KeyEvent {
-    Down(Key),
-    Up(Key),
+    /// Key pressed, with the character it types if any.
+    Down(Key, Option<char>),
+    Up(Key, Option<char>),
+    /// Mouse positions are fractions of the game's width and height, see
+    /// [`crate::utils::coordinates`].
     MouseMove(f64, f64),
-    MouseDown(f64, f64),
-    MouseUp(f64, f64),
+    MouseDown(f64, f64, MouseButton),
+    MouseUp(f64, f64, MouseButton),
+    MouseWheel(WheelDelta),
+    MouseLeave,
+    /// Movement while the pointer is locked, as a fraction of the game's size.
+    /// The host moves its virtual cursor by it.
+    MouseMoveBy(f64, f64),
+    /// Button pressed at the host's virtual cursor.
+    CursorDown(MouseButton),
+    CursorUp(MouseButton),
+}
+
+impl KeyEvent {
+    /// Where the mouse points for absolute mouse events.
+    pub fn position(&self) -> Option<(f64, f64)> {
+        match self {
+            KeyEvent::MouseMove(x, y)
+            | KeyEvent::MouseDown(x, y, _)
+            | KeyEvent::MouseUp(x, y, _) => Some((*x, *y)),
+            _ => None,
+        }
+    }
+
+    /// Event letting go of what this one presses, `None` if it presses nothing.
+    pub fn release(&self) -> Option<KeyEvent> {
+        match self {
+            KeyEvent::Down(key, key_char) => Some(KeyEvent::Up(*key, *key_char)),
+            KeyEvent::MouseDown(x, y, button) => Some(KeyEvent::MouseUp(*x, *y, *button)),
+            KeyEvent::CursorDown(button) => Some(KeyEvent::CursorUp(*button)),
+            _ => None,
+        }
+    }
+
+    /// Whether this lets go of the key or button `press` pressed.
+    pub fn releases(&self, press: &KeyEvent) -> bool {
+        match (press, self) {
+            (KeyEvent::Down(pressed, _), KeyEvent::Up(released, _)) => pressed == released,
+            (KeyEvent::MouseDown(.., pressed), KeyEvent::MouseUp(.., released))
+            | (KeyEvent::CursorDown(pressed), KeyEvent::CursorUp(released)) => pressed == released,
+            _ => false,
+        }
+    }
+
+    /// Whether the event lets go of a key or button. These are never dropped
+    /// on the way to the host, or the key would stay held.
+    pub fn is_release(&self) -> bool {
+        matches!(
+            self,
+            KeyEvent::Up(..) | KeyEvent::MouseUp(..) | KeyEvent::CursorUp(_) | KeyEvent::MouseLeave
+        )
+    }
+}
+
+/// Whether relayed input is a [`KeyEvent::is_release`].
+pub fn is_release_input(data: &[u8]) -> bool {
+    bincode::deserialize::<KeyEvent>(data).is_ok_and(|event| event.is_release())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+impl MouseButton {
+    /// Button of a DOM mouse event, back and forward buttons aren't forwarded.
+    pub fn from_event(event: &web_sys::MouseEvent) -> Option<Self> {
+        match event.button() {
+            0 => Some(MouseButton::Left),
+            1 => Some(MouseButton::Middle),
+            2 => Some(MouseButton::Right),
+            _ => None,
+        }
+    }
+}
+
+/// Scroll amount, positive when scrolling up like in Flash.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WheelDelta {
+    Lines(f64),
+    Pixels(f64),
+}
+
+impl WheelDelta {
+    pub fn from_event(event: &web_sys::WheelEvent) -> Option<Self> {
+        match event.delta_mode() {
+            web_sys::WheelEvent::DOM_DELTA_LINE => Some(WheelDelta::Lines(-event.delta_y())),
+            web_sys::WheelEvent::DOM_DELTA_PIXEL => Some(WheelDelta::Pixels(-event.delta_y())),
+            _ => None,
+        }
+    }
 }
 
 impl TryFrom<web_sys::KeyboardEvent> for Key {
@@ -94,6 +261,80 @@ impl TryFrom<web_sys::KeyboardEvent> for Key {
             "ArrowDown" => Ok(Key::DownArrow),
             "ArrowLeft" => Ok(Key::LeftArrow),
             "ArrowRight" => Ok(Key::RightArrow),
+
+            "Digit0" => Ok(Key::Digit0),
+            "Digit1" => Ok(Key::Digit1),
+            "Digit2" => Ok(Key::Digit2),
+            "Digit3" => Ok(Key::Digit3),
+            "Digit4" => Ok(Key::Digit4),
+            "Digit5" => Ok(Key::Digit5),
+            "Digit6" => Ok(Key::Digit6),
+            "Digit7" => Ok(Key::Digit7),
+            "Digit8" => Ok(Key::Digit8),
+            "Digit9" => Ok(Key::Digit9),
+
+            "ShiftLeft" => Ok(Key::ShiftLeft),
+            "ShiftRight" => Ok(Key::ShiftRight),
+            "AltLeft" => Ok(Key::AltLeft),
+            "AltRight" => Ok(Key::AltRight),
+            "MetaLeft" => Ok(Key::MetaLeft),
+            "MetaRight" => Ok(Key::MetaRight),
+
+            "Tab" => Ok(Key::Tab),
+            "Escape" => Ok(Key::Escape),
+            "Backspace" => Ok(Key::Backspace),
+            "CapsLock" => Ok(Key::CapsLock),
+            "Insert" => Ok(Key::Insert),
+            "Delete" => Ok(Key::Delete),
+            "Home" => Ok(Key::Home),
+            "End" => Ok(Key::End),
+            "PageUp" => Ok(Key::PageUp),
+            "PageDown" => Ok(Key::PageDown),
+            "Pause" => Ok(Key::Pause),
+            "NumLock" => Ok(Key::NumLock),
+            "ScrollLock" => Ok(Key::ScrollLock),
+
+            "F1" => Ok(Key::F1),
+            "F2" => Ok(Key::F2),
+            "F3" => Ok(Key::F3),
+            "F4" => Ok(Key::F4),
+            "F5" => Ok(Key::F5),
+            "F6" => Ok(Key::F6),
+            "F7" => Ok(Key::F7),
+            "F8" => Ok(Key::F8),
+            "F9" => Ok(Key::F9),
+            "F10" => Ok(Key::F10),
+            "F11" => Ok(Key::F11),
+            "F12" => Ok(Key::F12),
+
+            "Semicolon" => Ok(Key::Semicolon),
+            "Equal" => Ok(Key::Equal),
+            "Comma" => Ok(Key::Comma),
+            "Minus" => Ok(Key::Minus),
+            "Period" => Ok(Key::Period),
+            "Slash" => Ok(Key::Slash),
+            "Backquote" => Ok(Key::Backquote),
+            "BracketLeft" => Ok(Key::BracketLeft),
+            "Backslash" => Ok(Key::Backslash),
+            "BracketRight" => Ok(Key::BracketRight),
+            "Quote" => Ok(Key::Quote),
+
+            "Numpad0" => Ok(Key::Numpad0),
+            "Numpad1" => Ok(Key::Numpad1),
+            "Numpad2" => Ok(Key::Numpad2),
+            "Numpad3" => Ok(Key::Numpad3),
+            "Numpad4" => Ok(Key::Numpad4),
+            "Numpad5" => Ok(Key::Numpad5),
+            "Numpad6" => Ok(Key::Numpad6),
+            "Numpad7" => Ok(Key::Numpad7),
+            "Numpad8" => Ok(Key::Numpad8),
+            "Numpad9" => Ok(Key::Numpad9),
+            "NumpadMultiply" => Ok(Key::NumpadMultiply),
+            "NumpadAdd" => Ok(Key::NumpadAdd),
+            "NumpadSubtract" => Ok(Key::NumpadSubtract),
+            "NumpadDecimal" => Ok(Key::NumpadDecimal),
+            "NumpadDivide" => Ok(Key::NumpadDivide),
+            "NumpadEnter" => Ok(Key::NumpadEnter),
             _ => Err(()),
         }
     }
@@ -139,6 +380,176 @@ impl Key {
 
             Key::CtrlLeft => "Ctrl L".to_string(),
             Key::CtrlRight => "Ctrl R".to_string(),
+
+            Key::Digit0 => "0".to_string(),
+            Key::Digit1 => "1".to_string(),
+            Key::Digit2 => "2".to_string(),
+            Key::Digit3 => "3".to_string(),
+            Key::Digit4 => "4".to_string(),
+            Key::Digit5 => "5".to_string(),
+            Key::Digit6 => "6".to_string(),
+            Key::Digit7 => "7".to_string(),
+            Key::Digit8 => "8".to_string(),
+            Key::Digit9 => "9".to_string(),
+
+            Key::ShiftLeft => "Shift L".to_string(),
+            Key::ShiftRight => "Shift R".to_string(),
+            Key::AltLeft => "Alt L".to_string(),
+            Key::AltRight => "Alt R".to_string(),
+            Key::MetaLeft => "Meta L".to_string(),
+            Key::MetaRight => "Meta R".to_string(),
+
+            Key::Tab => "⇥".to_string(),
+            Key::Escape => "Esc".to_string(),
+            Key::Backspace => "⌫".to_string(),
+            Key::CapsLock => "⇪".to_string(),
+            Key::Insert => "Ins".to_string(),
+            Key::Delete => "Del".to_string(),
+            Key::Home => "Home".to_string(),
+            Key::End => "End".to_string(),
+            Key::PageUp => "PgUp".to_string(),
+            Key::PageDown => "PgDn".to_string(),
+            Key::Pause => "Pause".to_string(),
+            Key::NumLock => "Num".to_string(),
+            Key::ScrollLock => "Scroll".to_string(),
+
+            Key::F1 => "F1".to_string(),
+            Key::F2 => "F2".to_string(),
+            Key::F3 => "F3".to_string(),
+            Key::F4 => "F4".to_string(),
+            Key::F5 => "F5".to_string(),
+            Key::F6 => "F6".to_string(),
+            Key::F7 => "F7".to_string(),
+            Key::F8 => "F8".to_string(),
+            Key::F9 => "F9".to_string(),
+            Key::F10 => "F10".to_string(),
+            Key::F11 => "F11".to_string(),
+            Key::F12 => "F12".to_string(),
+
+            Key::Semicolon => ";".to_string(),
+            Key::Equal => "=".to_string(),
+            Key::Comma => ",".to_string(),
+            Key::Minus => "-".to_string(),
+            Key::Period => ".".to_string(),
+            Key::Slash => "/".to_string(),
+            Key::Backquote => "`".to_string(),
+            Key::BracketLeft => "[".to_string(),
+            Key::Backslash => "\\".to_string(),
+            Key::BracketRight => "]".to_string(),
+            Key::Quote => "'".to_string(),
+
+            Key::Numpad0 => "Num 0".to_string(),
+            Key::Numpad1 => "Num 1".to_string(),
+            Key::Numpad2 => "Num 2".to_string(),
+            Key::Numpad3 => "Num 3".to_string(),
+            Key::Numpad4 => "Num 4".to_string(),
+            Key::Numpad5 => "Num 5".to_string(),
+            Key::Numpad6 => "Num 6".to_string(),
+            Key::Numpad7 => "Num 7".to_string(),
+            Key::Numpad8 => "Num 8".to_string(),
+            Key::Numpad9 => "Num 9".to_string(),
+            Key::NumpadMultiply => "Num *".to_string(),
+            Key::NumpadAdd => "Num +".to_string(),
+            Key::NumpadSubtract => "Num -".to_string(),
+            Key::NumpadDecimal => "Num .".to_string(),
+            Key::NumpadDivide => "Num /".to_string(),
+            Key::NumpadEnter => "Num ↵".to_string(),
+        }
+    }
+
+    /// Character typed by the key without any modifiers, used when the
+    /// event doesn't come from a keyboard.
+    pub fn char(&self) -> Option<char> {
+        match self {
+            Key::A => Some('a'),
+            Key::B => Some('b'),
+            Key::C => Some('c'),
+            Key::D => Some('d'),
+            Key::E => Some('e'),
+            Key::F => Some('f'),
+            Key::G => Some('g'),
+            Key::H => Some('h'),
+            Key::I => Some('i'),
+            Key::J => Some('j'),
+            Key::K => Some('k'),
+            Key::L => Some('l'),
+            Key::M => Some('m'),
+            Key::N => Some('n'),
+            Key::O => Some('o'),
+            Key::P => Some('p'),
+            Key::Q => Some('q'),
+            Key::R => Some('r'),
+            Key::S => Some('s'),
+            Key::T => Some('t'),
+            Key::U => Some('u'),
+            Key::V => Some('v'),
+            Key::W => Some('w'),
+            Key::X => Some('x'),
+            Key::Y => Some('y'),
+            Key::Z => Some('z'),
+
+            Key::Space => Some(' '),
+            Key::Enter => Some('\r'),
+
+            Key::Digit0 => Some('0'),
+            Key::Digit1 => Some('1'),
+            Key::Digit2 => Some('2'),
+            Key::Digit3 => Some('3'),
+            Key::Digit4 => Some('4'),
+            Key::Digit5 => Some('5'),
+            Key::Digit6 => Some('6'),
+            Key::Digit7 => Some('7'),
+            Key::Digit8 => Some('8'),
+            Key::Digit9 => Some('9'),
+
+            Key::Tab => Some('\t'),
+            Key::Escape => Some('\u{1b}'),
+            Key::Backspace => Some('\u{8}'),
+            Key::Delete => Some('\u{7f}'),
+
+            Key::Semicolon => Some(';'),
+            Key::Equal => Some('='),
+            Key::Comma => Some(','),
+            Key::Minus => Some('-'),
+            Key::Period => Some('.'),
+            Key::Slash => Some('/'),
+            Key::Backquote => Some('`'),
+            Key::BracketLeft => Some('['),
+            Key::Backslash => Some('\\'),
+            Key::BracketRight => Some(']'),
+            Key::Quote => Some('\''),
+
+            Key::Numpad0 => Some('0'),
+            Key::Numpad1 => Some('1'),
+            Key::Numpad2 => Some('2'),
+            Key::Numpad3 => Some('3'),
+            Key::Numpad4 => Some('4'),
+            Key::Numpad5 => Some('5'),
+            Key::Numpad6 => Some('6'),
+            Key::Numpad7 => Some('7'),
+            Key::Numpad8 => Some('8'),
+            Key::Numpad9 => Some('9'),
+            Key::NumpadMultiply => Some('*'),
+            Key::NumpadAdd => Some('+'),
+            Key::NumpadSubtract => Some('-'),
+            Key::NumpadDecimal => Some('.'),
+            Key::NumpadDivide => Some('/'),
+            Key::NumpadEnter => Some('\r'),
+
+            _ => None,
         }
     }
 }
+
+/// Character typed by a keyboard event, for text input in the game.
+pub fn key_char(event: &web_sys::KeyboardEvent) -> Option<char> {
+    if event.ctrl_key() || event.meta_key() {
+        return None;
+    }
+    let key = event.key();
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(char), None) => Some(char),
+        _ => Key::try_from(event.clone()).ok().and_then(|key| key.char()),
+    }
+}
@@ -1 +1,8 @@
+pub mod chat_command;
+pub mod coordinates;
+pub mod gamepad_layout;
+pub mod gamepad_mapping;
+pub mod invite;
 pub mod keycode;
+pub mod profile;
+pub mod touch_gestures;
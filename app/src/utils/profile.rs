@@ -0,0 +1,27 @@
+use codee::string::JsonSerdeCodec;
+use common::{Profile, UserMeta};
+use leptos::{Signal, WriteSignal};
+use leptos_use::storage::use_local_storage;
+
+/// Local storage key of the last profile used, prefilled in the home page dialogs.
+pub const PROFILE_KEY: &str = "profile";
+
+pub fn use_stored_profile() -> (Signal<Profile>, WriteSignal<Profile>) {
+    let (profile, set_profile, _) = use_local_storage::<Profile, JsonSerdeCodec>(PROFILE_KEY);
+    (profile, set_profile)
+}
+
+/// Color of a user, derived from their id unless they picked one.
+pub fn user_color(user: &UserMeta) -> String {
+    user.color
+        .clone()
+        .unwrap_or_else(|| format!("hsl({}, 80%, 60%)", user.id.as_u128() % 360))
+}
+
+/// Name with the user's avatar in front.
+pub fn display_name(user: &UserMeta) -> String {
+    match &user.emoji {
+        Some(emoji) => format!("{emoji} {}", user.name),
+        None => user.name.clone(),
+    }
+}
@@ -0,0 +1,546 @@
+use std::collections::HashMap;
+
+use codee::string::JsonSerdeCodec;
+use leptos::*;
+use leptos_use::{
+    storage::use_local_storage, use_event_listener_with_options, UseEventListenerOptions,
+};
+use serde::{Deserialize, Serialize};
+use web_sys::{js_sys::Date, HtmlElement};
+
+use crate::utils::{
+    coordinates::{normalize, normalize_clamped, Rect},
+    keycode::{KeyEvent, MouseButton, WheelDelta},
+};
+
+const TOUCH_GESTURES_KEY: &str = "touch_gestures";
+
+/// Longest touch, in milliseconds, that still counts as a tap.
+const TAP_DURATION_MS: f64 = 300.0;
+/// How far, in pixels, fingers may move during a tap.
+const TAP_DISTANCE: f64 = 15.0;
+
+/// What multi finger gestures on the game turn into. Flash only knows a
+/// single mouse and ruffle has no touch events, so gestures are mapped onto
+/// mouse input.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TouchGestureConfig {
+    /// Button clicked by a two finger tap
+    pub two_finger_tap: Option<MouseButton>,
+    /// Whether pinching scrolls the mouse wheel, spreading scrolls up
+    pub pinch_to_wheel: bool,
+    /// Change in finger distance, in pixels, per wheel line
+    pub pinch_step: f64,
+}
+
+impl Default for TouchGestureConfig {
+    fn default() -> Self {
+        Self {
+            two_finger_tap: Some(MouseButton::Right),
+            pinch_to_wheel: true,
+            pinch_step: 30.0,
+        }
+    }
+}
+
+/// Position of a touch on the page and on the game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchPoint {
+    pub client: (f64, f64),
+    pub game: (f64, f64),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TrackedTouch {
+    start: TouchPoint,
+    last: TouchPoint,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Gesture {
+    started_at: f64,
+    /// Finger distance the last wheel line was sent at
+    pinch_distance: f64,
+    /// Whether the fingers moved too much for a tap
+    moved: bool,
+}
+
+/// Follows touches by identifier and turns them into mouse input.
+///
+/// The first finger drags the mouse with the left button held. A second
+/// finger releases it and starts a gesture that lasts until every finger is
+/// lifted, fingers past the second are ignored.
+#[derive(Debug, Default)]
+pub struct TouchTracker {
+    touches: HashMap<i32, TrackedTouch>,
+    /// Order the tracked fingers landed in
+    order: Vec<i32>,
+    gesture: Option<Gesture>,
+}
+
+impl TouchTracker {
+    pub fn start(
+        &mut self,
+        id: i32,
+        point: TouchPoint,
+        now: f64,
+        config: &TouchGestureConfig,
+    ) -> Vec<KeyEvent> {
+        let has_gestures = config.two_finger_tap.is_some() || config.pinch_to_wheel;
+        if self.order.len() >= 2
+            || (!self.order.is_empty() && !has_gestures)
+            || self.touches.contains_key(&id)
+        {
+            return vec![];
+        }
+        self.touches.insert(
+            id,
+            TrackedTouch {
+                start: point,
+                last: point,
+            },
+        );
+        self.order.push(id);
+
+        match (self.order.as_slice(), self.gesture) {
+            ([_], None) => vec![KeyEvent::MouseDown(
+                point.game.0,
+                point.game.1,
+                MouseButton::Left,
+            )],
+            ([first, second], None) => {
+                let first = self.touches[first].last;
+                let second = self.touches[second].last;
+                self.gesture = Some(Gesture {
+                    started_at: now,
+                    pinch_distance: distance(first.client, second.client),
+                    moved: false,
+                });
+                vec![KeyEvent::MouseUp(
+                    first.game.0,
+                    first.game.1,
+                    MouseButton::Left,
+                )]
+            }
+            // A finger landing while the rest of a gesture is still lifting
+            _ => vec![],
+        }
+    }
+
+    /// Whether the touch is followed, moves of other touches are ignored.
+    pub fn is_tracking(&self, id: i32) -> bool {
+        self.touches.contains_key(&id)
+    }
+
+    pub fn update(
+        &mut self,
+        id: i32,
+        point: TouchPoint,
+        config: &TouchGestureConfig,
+    ) -> Vec<KeyEvent> {
+        let Some(touch) = self.touches.get_mut(&id) else {
+            return vec![];
+        };
+        touch.last = point;
+        let moved = distance(touch.start.client, point.client) > TAP_DISTANCE;
+
+        let Some(gesture) = self.gesture.as_mut() else {
+            return vec![KeyEvent::MouseMove(point.game.0, point.game.1)];
+        };
+        gesture.moved |= moved;
+
+        let [first, second] = self.order.as_slice() else {
+            return vec![];
+        };
+        if !config.pinch_to_wheel || config.pinch_step <= 0.0 {
+            return vec![];
+        }
+        let current = distance(
+            self.touches[first].last.client,
+            self.touches[second].last.client,
+        );
+        let lines = ((current - gesture.pinch_distance) / config.pinch_step).trunc();
+        if lines == 0.0 {
+            return vec![];
+        }
+        gesture.pinch_distance += lines * config.pinch_step;
+        vec![KeyEvent::MouseWheel(WheelDelta::Lines(lines))]
+    }
+
+    pub fn end(
+        &mut self,
+        id: i32,
+        point: Option<TouchPoint>,
+        now: f64,
+        config: &TouchGestureConfig,
+    ) -> Vec<KeyEvent> {
+        let Some(touch) = self.touches.get(&id).copied() else {
+            return vec![];
+        };
+        let point = point.unwrap_or(touch.last);
+        let mut events = vec![];
+
+        match self.gesture {
+            None => events.push(KeyEvent::MouseUp(
+                point.game.0,
+                point.game.1,
+                MouseButton::Left,
+            )),
+            Some(gesture) => {
+                let is_tap = !gesture.moved
+                    && distance(touch.start.client, point.client) <= TAP_DISTANCE
+                    && now - gesture.started_at <= TAP_DURATION_MS;
+                // The tap fires when the first of the two fingers lifts
+                if let (Some(button), true, [first, second]) =
+                    (config.two_finger_tap, is_tap, self.order.as_slice())
+                {
+                    let (a, b) = (
+                        self.touches[first].last.game,
+                        self.touches[second].last.game,
+                    );
+                    let (x, y) = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+                    events.push(KeyEvent::MouseDown(x, y, button));
+                    events.push(KeyEvent::MouseUp(x, y, button));
+                }
+                if let Some(gesture) = self.gesture.as_mut() {
+                    gesture.moved = true;
+                }
+            }
+        }
+
+        self.touches.remove(&id);
+        self.order.retain(|touch| *touch != id);
+        if self.order.is_empty() {
+            self.gesture = None;
+        }
+        events
+    }
+}
+
+type TouchHandler<'a> =
+    &'a dyn Fn(&mut TouchTracker, &TouchGestureConfig, web_sys::Touch) -> Vec<KeyEvent>;
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+/// Tracks touches on `el` and sends the resulting mouse input, `area` is
+/// the part of the page showing the game.
+pub fn use_touch_input(
+    el: HtmlElement,
+    area: fn(&HtmlElement) -> Rect,
+    events_tx: WriteSignal<Option<KeyEvent>>,
+) {
+    let (config, _, _) =
+        use_local_storage::<TouchGestureConfig, JsonSerdeCodec>(TOUCH_GESTURES_KEY);
+    let tracker = store_value(TouchTracker::default());
+
+    // Runs `f` for every changed touch and sends the events it returns
+    let handle = move |ev: &web_sys::TouchEvent, f: TouchHandler| {
+        let touches = ev.changed_touches();
+        for touch in (0..touches.length()).filter_map(|i| touches.get(i)) {
+            let events = config.with_untracked(|config| {
+                tracker
+                    .try_update_value(|tracker| f(tracker, config, touch))
+                    .unwrap_or_default()
+            });
+            for event in events {
+                events_tx.set(Some(event));
+            }
+        }
+    };
+
+    let target = el.clone();
+    let _ = use_event_listener_with_options(
+        el.clone(),
+        leptos::ev::touchstart,
+        move |ev| {
+            handle(
+                &ev,
+                &|tracker, config, touch| match touch_point(area(&target), &touch) {
+                    Some(point) => {
+                        ev.prevent_default();
+                        tracker.start(touch.identifier(), point, Date::now(), config)
+                    }
+                    None => vec![],
+                },
+            );
+        },
+        UseEventListenerOptions::default().passive(false),
+    );
+
+    let target = el.clone();
+    let _ = use_event_listener_with_options(
+        el.clone(),
+        leptos::ev::touchmove,
+        move |ev| {
+            handle(&ev, &|tracker, config, touch| {
+                if !tracker.is_tracking(touch.identifier()) {
+                    return vec![];
+                }
+                // A finger dragged off the game keeps the mouse on its edge
+                ev.prevent_default();
+                let point = touch_point_clamped(area(&target), &touch);
+                tracker.update(touch.identifier(), point, config)
+            });
+        },
+        UseEventListenerOptions::default().passive(false),
+    );
+
+    let on_end = move |target: HtmlElement| {
+        move |ev: web_sys::TouchEvent| {
+            handle(&ev, &|tracker, config, touch| {
+                // Lifting outside the game still releases the mouse
+                let point = touch_point(area(&target), &touch);
+                if point.is_some() {
+                    ev.prevent_default();
+                }
+                tracker.end(touch.identifier(), point, Date::now(), config)
+            });
+        }
+    };
+    let _ = use_event_listener_with_options(
+        el.clone(),
+        leptos::ev::touchend,
+        on_end(el.clone()),
+        UseEventListenerOptions::default().passive(false),
+    );
+    let _ = use_event_listener_with_options(
+        el.clone(),
+        leptos::ev::touchcancel,
+        on_end(el),
+        UseEventListenerOptions::default().passive(false),
+    );
+}
+
+fn touch_point(area: Rect, touch: &web_sys::Touch) -> Option<TouchPoint> {
+    let client = (f64::from(touch.client_x()), f64::from(touch.client_y()));
+    normalize(client, area).map(|game| TouchPoint { client, game })
+}
+
+fn touch_point_clamped(area: Rect, touch: &web_sys::Touch) -> TouchPoint {
+    let client = (f64::from(touch.client_x()), f64::from(touch.client_y()));
+    TouchPoint {
+        client,
+        game: normalize_clamped(client, area),
+    }
+}
+
+/// How multi finger gestures on the game are mapped to the mouse.
+#[component]
+pub fn TouchGestureSettings() -> impl IntoView {
+    let (config, set_config, _) =
+        use_local_storage::<TouchGestureConfig, JsonSerdeCodec>(TOUCH_GESTURES_KEY);
+
+    view! {
+        <div class="border border-white/40 p-2 flex flex-col gap-1 text-xs">
+            <span class="font-bold2">"Touch"</span>
+            <label class="flex items-center gap-2">
+                "Two finger tap"
+                <div class="flex-grow" />
+                <select
+                    class="bg-blue-500 px-1 py-1 active:bg-blue-900"
+                    on:change=move |ev| {
+                        let button = match event_target_value(&ev).as_str() {
+                            "left" => Some(MouseButton::Left),
+                            "middle" => Some(MouseButton::Middle),
+                            "right" => Some(MouseButton::Right),
+                            _ => None,
+                        };
+                        set_config.update(|config| config.two_finger_tap = button);
+                    }
+                >
+                    {[
+                        ("none", "Nothing", None),
+                        ("left", "Left click", Some(MouseButton::Left)),
+                        ("middle", "Middle click", Some(MouseButton::Middle)),
+                        ("right", "Right click", Some(MouseButton::Right)),
+                    ]
+                        .into_iter()
+                        .map(|(value, title, button)| {
+                            view! {
+                                <option
+                                    value=value
+                                    selected=move || config.with(|c| c.two_finger_tap == button)
+                                >
+                                    {title}
+                                </option>
+                            }
+                        })
+                        .collect_view()}
+                </select>
+            </label>
+            <label class="flex items-center gap-2">
+                "Pinch to scroll"
+                <div class="flex-grow" />
+                <input
+                    type="checkbox"
+                    prop:checked=move || config.with(|c| c.pinch_to_wheel)
+                    on:change=move |ev| {
+                        let checked = event_target_checked(&ev);
+                        set_config.update(|config| config.pinch_to_wheel = checked);
+                    }
+                />
+            </label>
+            <label class="flex items-center gap-2">
+                "Pinch distance per scroll (px)"
+                <div class="flex-grow" />
+                <input
+                    class="bg-white/10 w-16 p-1"
+                    type="number"
+                    min="5"
+                    step="5"
+                    prop:value=move || config.with(|c| c.pinch_step)
+                    on:change=move |ev| {
+                        if let Ok(value) = event_target_value(&ev).parse::<f64>() {
+                            set_config.update(|config| config.pinch_step = value.max(5.0));
+                        }
+                    }
+                />
+            </label>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f64, y: f64) -> TouchPoint {
+        TouchPoint {
+            client: (x, y),
+            game: (x / 100.0, y / 100.0),
+        }
+    }
+
+    #[test]
+    fn one_finger_drags_with_left_button() {
+        let config = TouchGestureConfig::default();
+        let mut tracker = TouchTracker::default();
+        assert_eq!(
+            tracker.start(1, point(10.0, 20.0), 0.0, &config),
+            vec![KeyEvent::MouseDown(0.1, 0.2, MouseButton::Left)]
+        );
+        assert_eq!(
+            tracker.update(1, point(50.0, 20.0), &config),
+            vec![KeyEvent::MouseMove(0.5, 0.2)]
+        );
+        assert_eq!(tracker.update(2, point(0.0, 0.0), &config), vec![]);
+        assert_eq!(
+            tracker.end(1, None, 1000.0, &config),
+            vec![KeyEvent::MouseUp(0.5, 0.2, MouseButton::Left)]
+        );
+        assert!(!tracker.is_tracking(1));
+    }
+
+    #[test]
+    fn second_finger_releases_mouse() {
+        let config = TouchGestureConfig::default();
+        let mut tracker = TouchTracker::default();
+        tracker.start(1, point(10.0, 10.0), 0.0, &config);
+        tracker.update(1, point(20.0, 10.0), &config);
+        assert_eq!(
+            tracker.start(2, point(80.0, 10.0), 10.0, &config),
+            vec![KeyEvent::MouseUp(0.2, 0.1, MouseButton::Left)]
+        );
+        // Fingers past the second are ignored
+        assert_eq!(tracker.start(3, point(50.0, 50.0), 20.0, &config), vec![]);
+        assert!(!tracker.is_tracking(3));
+
+        // Moving within the gesture no longer moves the mouse
+        assert_eq!(tracker.update(1, point(22.0, 10.0), &config), vec![]);
+    }
+
+    #[test]
+    fn second_finger_ignored_without_gestures() {
+        let config = TouchGestureConfig {
+            two_finger_tap: None,
+            pinch_to_wheel: false,
+            ..Default::default()
+        };
+        let mut tracker = TouchTracker::default();
+        tracker.start(1, point(10.0, 10.0), 0.0, &config);
+        assert_eq!(tracker.start(2, point(80.0, 10.0), 10.0, &config), vec![]);
+        assert_eq!(
+            tracker.update(1, point(20.0, 10.0), &config),
+            vec![KeyEvent::MouseMove(0.2, 0.1)]
+        );
+    }
+
+    #[test]
+    fn two_finger_tap_clicks_between_fingers() {
+        let config = TouchGestureConfig::default();
+        let mut tracker = TouchTracker::default();
+        tracker.start(1, point(20.0, 40.0), 0.0, &config);
+        tracker.start(2, point(60.0, 40.0), 10.0, &config);
+        assert_eq!(
+            tracker.end(2, None, 100.0, &config),
+            vec![
+                KeyEvent::MouseDown(0.4, 0.4, MouseButton::Right),
+                KeyEvent::MouseUp(0.4, 0.4, MouseButton::Right),
+            ]
+        );
+        // Only one click for the pair, and no left release
+        assert_eq!(tracker.end(1, None, 110.0, &config), vec![]);
+
+        // The next finger starts a fresh drag
+        assert_eq!(
+            tracker.start(3, point(10.0, 10.0), 200.0, &config),
+            vec![KeyEvent::MouseDown(0.1, 0.1, MouseButton::Left)]
+        );
+    }
+
+    #[test]
+    fn slow_or_moved_two_finger_touch_is_no_tap() {
+        let config = TouchGestureConfig::default();
+        let mut tracker = TouchTracker::default();
+        tracker.start(1, point(20.0, 40.0), 0.0, &config);
+        tracker.start(2, point(60.0, 40.0), 10.0, &config);
+        assert_eq!(
+            tracker.end(2, None, 10.0 + TAP_DURATION_MS + 1.0, &config),
+            vec![]
+        );
+
+        tracker.end(1, None, 1000.0, &config);
+        tracker.start(1, point(20.0, 40.0), 2000.0, &config);
+        tracker.start(2, point(60.0, 40.0), 2010.0, &config);
+        tracker.update(2, point(60.0, 40.0 + TAP_DISTANCE + 1.0), &config);
+        tracker.update(2, point(60.0, 40.0), &config);
+        assert_eq!(tracker.end(2, None, 2050.0, &config), vec![]);
+    }
+
+    #[test]
+    fn pinch_scrolls_whole_lines() {
+        let config = TouchGestureConfig {
+            pinch_step: 30.0,
+            ..Default::default()
+        };
+        let mut tracker = TouchTracker::default();
+        tracker.start(1, point(0.0, 0.0), 0.0, &config);
+        tracker.start(2, point(100.0, 0.0), 0.0, &config);
+
+        // Less than a step sends nothing
+        assert_eq!(tracker.update(2, point(120.0, 0.0), &config), vec![]);
+        // Spreading scrolls up, the leftover distance carries over
+        assert_eq!(
+            tracker.update(2, point(165.0, 0.0), &config),
+            vec![KeyEvent::MouseWheel(WheelDelta::Lines(2.0))]
+        );
+        assert_eq!(
+            tracker.update(2, point(190.0, 0.0), &config),
+            vec![KeyEvent::MouseWheel(WheelDelta::Lines(1.0))]
+        );
+        // Pinching scrolls down
+        assert_eq!(
+            tracker.update(2, point(100.0, 0.0), &config),
+            vec![KeyEvent::MouseWheel(WheelDelta::Lines(-3.0))]
+        );
+
+        let config = TouchGestureConfig {
+            pinch_to_wheel: false,
+            ..config
+        };
+        assert_eq!(tracker.update(2, point(300.0, 0.0), &config), vec![]);
+    }
+}
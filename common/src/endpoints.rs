@@ -1,2 +1,4 @@
 pub static HOST_ROOM: &str = "/hoost-room/ws";
 pub static JOIN_ROOM: &str = "/join-room/ws";
+/// JSON list of [`crate::room_list::PublicRoom`]s.
+pub static ROOM_LIST: &str = "/room-list";
@@ -2,9 +2,13 @@ pub mod endpoints;
 pub mod message;
 pub mod message_sender;
 pub mod params;
+pub mod protocol;
+pub mod room_list;
 pub mod util;
 
-use message::Message;
+use std::{collections::VecDeque, time::Instant};
+
+use message::{ChatEntry, Message, ServerError};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -44,26 +48,239 @@ pub struct UserMeta {
     pub id: Uuid,
     pub name: String,
     pub state: UserState,
+    /// Last round trip time to the server reported by the user, in milliseconds.
+    #[serde(default)]
+    pub latency: Option<u32>,
+    /// Player slot claimed by the user, starting at 1
+    #[serde(default)]
+    pub slot: Option<u8>,
+    /// `#rrggbb` picked by the user
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Emoji shown as the user's avatar
+    #[serde(default)]
+    pub emoji: Option<String>,
+    #[serde(default)]
+    pub role: UserRole,
+}
+
+/// Whether a user gets the game stream. Spectators are users who joined a
+/// full room, they wait in join order for a player to leave.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UserRole {
+    #[default]
+    Player,
+    Spectator,
+}
+
+impl UserRole {
+    /// Returns `true` if the user role is [`Spectator`].
+    ///
+    /// [`Spectator`]: UserRole::Spectator
+    #[must_use]
+    pub fn is_spectator(&self) -> bool {
+        matches!(self, Self::Spectator)
+    }
+}
+
+/// How many users a room takes, picked by the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomLimits {
+    /// Users getting the game stream, the host included
+    pub max_players: u8,
+    /// Users waiting for a player spot, unlimited when `None`
+    pub max_spectators: Option<u8>,
+}
+
+impl Default for RoomLimits {
+    fn default() -> Self {
+        Self {
+            max_players: DEFAULT_MAX_PLAYERS,
+            max_spectators: None,
+        }
+    }
+}
+
+impl UserMeta {
+    pub fn profile(&self) -> Profile {
+        Profile {
+            name: self.name.clone(),
+            color: self.color.clone(),
+            emoji: self.emoji.clone(),
+        }
+    }
+
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.name = profile.name;
+        self.color = profile.color;
+        self.emoji = profile.emoji;
+    }
+}
+
+/// How a user shows up in the room, picked by the user.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub emoji: Option<String>,
 }
 
+impl Profile {
+    /// Trims the fields and drops the blank ones.
+    pub fn normalized(self) -> Self {
+        let non_blank = |value: Option<String>| {
+            value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        Self {
+            name: self.name.trim().to_string(),
+            color: non_blank(self.color),
+            emoji: non_blank(self.emoji),
+        }
+    }
+
+    /// Checks a normalized profile, clashes with other users' names aside.
+    pub fn validate(&self) -> Result<(), ServerError> {
+        self.validate_name()?;
+        self.validate_looks()
+    }
+
+    pub fn validate_name(&self) -> Result<(), ServerError> {
+        if self.name.is_empty()
+            || self.name.chars().count() > MAX_NAME_LEN
+            || self
+                .name
+                .chars()
+                .any(|c| c.is_control() || is_format_char(c))
+        {
+            return Err(ServerError::InvalidName {
+                max_len: MAX_NAME_LEN,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks the color and avatar.
+    pub fn validate_looks(&self) -> Result<(), ServerError> {
+        self.validate_color()?;
+        self.validate_emoji()
+    }
+
+    pub fn validate_color(&self) -> Result<(), ServerError> {
+        if let Some(color) = &self.color {
+            let is_hex = color.len() == 7
+                && color.starts_with('#')
+                && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+            if !is_hex {
+                return Err(ServerError::InvalidColor);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate_emoji(&self) -> Result<(), ServerError> {
+        if let Some(emoji) = &self.emoji {
+            // Emoji sequences join several chars, anything readable isn't one.
+            // The zero width joiner and tags are the only format chars they use.
+            let is_invisible =
+                |c: char| is_format_char(c) && !matches!(c, '\u{200D}' | '\u{E0020}'..='\u{E007F}');
+            if emoji.chars().count() > MAX_EMOJI_LEN
+                || emoji.chars().any(|c| {
+                    c.is_alphanumeric() || c.is_whitespace() || c.is_ascii() || is_invisible(c)
+                })
+            {
+                return Err(ServerError::InvalidEmoji);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `c` is an invisible format char (Unicode category Cf), like zero
+/// width spaces and bidi overrides that make a name pass for another one.
+fn is_format_char(c: char) -> bool {
+    matches!(
+        c,
+        '\u{AD}'
+            | '\u{600}'..='\u{605}'
+            | '\u{61C}'
+            | '\u{6DD}'
+            | '\u{70F}'
+            | '\u{890}'..='\u{891}'
+            | '\u{8E2}'
+            | '\u{180E}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{206F}'
+            | '\u{FEFF}'
+            | '\u{FFF9}'..='\u{FFFB}'
+            | '\u{110BD}'
+            | '\u{110CD}'
+            | '\u{13430}'..='\u{1343F}'
+            | '\u{1BCA0}'..='\u{1BCA3}'
+            | '\u{1D173}'..='\u{1D17A}'
+            | '\u{E0001}'
+            | '\u{E0020}'..='\u{E007F}'
+    )
+}
+
+/// Longest display name accepted, in characters.
+pub const MAX_NAME_LEN: usize = 32;
+/// Longest emoji avatar accepted, in chars, enough for joined sequences.
+pub const MAX_EMOJI_LEN: usize = 8;
+/// Number of player slots users can claim.
+pub const MAX_SLOTS: u8 = 4;
+/// Longest room id, generated or picked by the host.
+pub const MAX_ROOM_ID_LEN: usize = 32;
+/// Shortest room code a host can pick.
+pub const MIN_ROOM_CODE_LEN: usize = 3;
+/// Players in a room when the host doesn't pick a limit.
+pub const DEFAULT_MAX_PLAYERS: u8 = 8;
+/// Most players a host can let in, each one costs the host a peer connection.
+pub const MAX_PLAYERS: u8 = 16;
+/// Longest title of a public room, in characters.
+pub const MAX_TITLE_LEN: usize = 48;
+
 pub struct Room {
+    /// Players first then spectators, both in join order, the host is the
+    /// first player
     pub users: Vec<User>,
     pub player_status: PlayerStatus,
     pub tracks: Option<(String, Vec<(Option<String>, Option<String>)>)>,
+    pub recommended_layout: Option<String>,
+    /// Latest chat messages, oldest first
+    pub chat_log: VecDeque<ChatEntry>,
+    pub next_chat_id: u64,
+    pub limits: RoomLimits,
+    /// Title in the room list, private rooms aren't listed
+    pub public_title: Option<String>,
+    pub created_at: Instant,
 }
 
 #[cfg(feature = "ssr")]
 mod ssr {
     use futures::{stream::FuturesUnordered, StreamExt, TryStreamExt};
-    use message::{RoomJoinInfo, RtcConfig};
+    use message::{ChatContent, RoomJoinInfo, RtcConfig};
+    use room_list::PublicRoom;
     use thiserror::Error;
     use tokio::sync::RwLock;
     use tracing::warn;
     use unicase::UniCase;
-    use util::generate_random_string;
 
     use super::*;
-    use std::{collections::HashMap, env::VarError, sync::Arc, time::SystemTimeError};
+    use std::{
+        collections::HashMap,
+        env::VarError,
+        sync::Arc,
+        time::{SystemTime, SystemTimeError, UNIX_EPOCH},
+    };
+
+    /// Chat messages kept per room for users joining later.
+    const CHAT_LOG_LEN: usize = 100;
 
     #[derive(Clone, Default)]
     pub struct RoomProvider {
@@ -76,6 +293,12 @@ mod ssr {
         KeyGenerationFailed,
         #[error("given room does not exist")]
         RoomDoesntExist,
+        #[error("room has no player or spectator spot left")]
+        RoomFull,
+        #[error("requested room id is already used")]
+        RoomIdTaken,
+        #[error("someone in the room already uses that name")]
+        NameTaken,
 
         #[error("RTCConfig Generation Failed")]
         RTCConfigGenerationFailed(#[from] VarError),
@@ -87,6 +310,14 @@ mod ssr {
         HmacError(#[from] sha1::digest::InvalidLength),
     }
 
+    /// How a new room is set up.
+    pub struct NewRoom {
+        pub limits: RoomLimits,
+        pub public_title: Option<String>,
+        /// Code picked by the host, a generated one is used when `None`
+        pub requested_id: Option<String>,
+    }
+
     impl RoomProvider {
         pub fn new() -> Self {
             Self {
@@ -94,12 +325,25 @@ mod ssr {
             }
         }
 
-        pub async fn new_room(&self, user: User) -> Result<RoomJoinInfo, RoomProviderError> {
+        /// Creates a room hosted by `user`, `generate_id` gives candidate ids
+        /// when the host didn't pick one.
+        pub async fn new_room(
+            &self,
+            user: User,
+            new_room: NewRoom,
+            generate_id: impl Fn() -> String,
+        ) -> Result<RoomJoinInfo, RoomProviderError> {
             let mut rooms = self.rooms.write().await;
-            let id = {
+            let id = if let Some(id) = new_room.requested_id {
+                let id = UniCase::from(id);
+                if rooms.contains_key(&id) {
+                    return Err(RoomProviderError::RoomIdTaken);
+                }
+                id
+            } else {
                 let mut tries = 5;
                 loop {
-                    let id = UniCase::from(generate_random_string(6));
+                    let id = UniCase::from(generate_id());
                     if !rooms.contains_key(&id) {
                         break id;
                     }
@@ -110,7 +354,7 @@ mod ssr {
                 }
             };
             let user_meta = user.meta.clone();
-            let room = Room::new(user);
+            let room = Room::new(user, new_room.limits, new_room.public_title);
             let player_status = room.player_status.clone();
             rooms.insert(id.clone(), room);
             let rtc_config = get_rtc_info(&user_meta.id.to_string()).await?;
@@ -120,17 +364,25 @@ mod ssr {
                 users: vec![user_meta],
                 player_status,
                 rtc_config,
+                recommended_layout: None,
+                chat_log: Vec::new(),
             })
         }
 
         pub async fn join_room(
             &self,
             room_id: &str,
-            user: User,
+            mut user: User,
         ) -> Result<RoomJoinInfo, RoomProviderError> {
             let mut rooms = self.rooms.write().await;
             let user_id = user.meta.id;
             if let Some(room) = rooms.get_mut(&UniCase::from(room_id)) {
+                if room.is_name_taken(&user.meta.name, user_id) {
+                    return Err(RoomProviderError::NameTaken);
+                }
+                user.meta.role = room
+                    .role_for_new_user()
+                    .ok_or(RoomProviderError::RoomFull)?;
                 room.users.push(user);
                 let rtc_config = get_rtc_info(&user_id.to_string()).await?;
                 Ok(RoomJoinInfo {
@@ -139,6 +391,8 @@ mod ssr {
                     users: room.users.iter().map(|u| u.meta.clone()).collect(),
                     player_status: room.player_status.clone(),
                     rtc_config,
+                    recommended_layout: room.recommended_layout.clone(),
+                    chat_log: room.chat_log.iter().cloned().collect(),
                 })
             } else {
                 Err(RoomProviderError::RoomDoesntExist)
@@ -151,50 +405,93 @@ mod ssr {
             message: Message,
             excluded_users: &[Uuid],
         ) {
-            let rooms = self.rooms.read().await;
-            if let Some(room) = rooms.get(&UniCase::from(room_id)) {
-                let send_futures = room
-                    .users
-                    .iter()
-                    .filter(|user| !excluded_users.contains(&user.meta.id))
-                    .map(|user| user.sender.send(message.clone()))
-                    .collect::<FuturesUnordered<_>>();
-
-                send_futures
-                    .into_stream()
-                    .for_each_concurrent(None, |data| async {
-                        if let Err(err) = data {
-                            warn!("broadcast failed {err:?}");
-                        }
-                    })
-                    .await;
-            }
+            // Senders are cloned so a slow client doesn't hold the rooms lock
+            let senders = self
+                .with_room(room_id, |room| {
+                    room.users
+                        .iter()
+                        .filter(|user| !excluded_users.contains(&user.meta.id))
+                        .map(|user| user.sender.clone())
+                        .collect::<Vec<_>>()
+                })
+                .await
+                .unwrap_or_default();
+
+            senders
+                .iter()
+                .map(|sender| sender.send(message.clone()))
+                .collect::<FuturesUnordered<_>>()
+                .into_stream()
+                .for_each_concurrent(None, |data| async {
+                    if let Err(err) = data {
+                        warn!("broadcast failed {err:?}");
+                    }
+                })
+                .await;
         }
         pub async fn send_msg_for_user(&self, room_id: &str, user_id: Uuid, message: Message) {
-            let rooms = self.rooms.read().await;
-            if let Some(room) = rooms.get(&UniCase::from(room_id)) {
-                if let Some(user) = room.users.iter().find(|u| u.meta.id == user_id) {
-                    if let Err(err) = user.sender.send(message).await {
-                        warn!("Failed to send msg to user {} {err:?}", user_id);
-                    }
+            let sender = self
+                .with_room(room_id, |room| {
+                    room.users
+                        .iter()
+                        .find(|u| u.meta.id == user_id)
+                        .map(|u| u.sender.clone())
+                })
+                .await
+                .flatten();
+            if let Some(sender) = sender {
+                if let Err(err) = sender.send(message).await {
+                    warn!("Failed to send msg to user {} {err:?}", user_id);
                 }
             }
         }
 
-        pub async fn remove_user(&self, room_id: &str, user_id: Uuid) -> Option<Vec<UserMeta>> {
+        /// Removes the user, promoting spectators to the freed player spot.
+        /// Returns the users left and the names of the promoted ones.
+        pub async fn remove_user(
+            &self,
+            room_id: &str,
+            user_id: Uuid,
+        ) -> Option<(Vec<UserMeta>, Vec<String>)> {
             let mut rooms = self.rooms.write().await;
             if let Some(room) = rooms.get_mut(&UniCase::from(room_id)) {
                 room.users.retain(|user| user.meta.id != user_id);
+                let promoted = room.promote_spectators();
                 let users = room.users.iter().map(|u| u.meta.clone()).collect();
                 if room.users.is_empty() {
                     rooms.remove(&UniCase::from(room_id));
                 }
-                Some(users)
+                Some((users, promoted))
             } else {
                 None
             }
         }
 
+        /// Rooms listed in the room list, the most players first.
+        pub async fn public_rooms(&self) -> Vec<PublicRoom> {
+            let rooms = self.rooms.read().await;
+            let mut public_rooms = rooms
+                .iter()
+                .filter_map(|(id, room)| {
+                    let title = room.public_title.clone()?;
+                    let game = room.users.first().and_then(|host| match &host.meta.state {
+                        UserState::VideoSelected(game) => Some(game.clone()),
+                        UserState::VideoNotSelected => None,
+                    });
+                    Some(PublicRoom {
+                        room_id: id.to_lowercase(),
+                        title,
+                        game,
+                        players: room.player_count(),
+                        spectators: room.spectator_count(),
+                        limits: room.limits,
+                        uptime_secs: room.created_at.elapsed().as_secs(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            public_rooms.sort_by_key(|r| std::cmp::Reverse(r.players));
+            public_rooms
+        }
         pub async fn get_room_player_status(&self, room_id: &str) -> Option<PlayerStatus> {
             let rooms = self.rooms.read().await;
             rooms
@@ -233,12 +530,84 @@ mod ssr {
     }
 
     impl Room {
-        pub fn new(user: User) -> Self {
+        pub fn new(user: User, limits: RoomLimits, public_title: Option<String>) -> Self {
             Self {
                 users: vec![user],
                 player_status: PlayerStatus::Paused(0.0),
                 tracks: None,
+                recommended_layout: None,
+                chat_log: VecDeque::new(),
+                next_chat_id: 0,
+                limits,
+                public_title,
+                created_at: Instant::now(),
+            }
+        }
+
+        pub fn player_count(&self) -> usize {
+            self.users
+                .iter()
+                .filter(|u| !u.meta.role.is_spectator())
+                .count()
+        }
+
+        pub fn spectator_count(&self) -> usize {
+            self.users.len() - self.player_count()
+        }
+
+        /// Whether anyone but `user_id` goes by `name`, ignoring case.
+        pub fn is_name_taken(&self, name: &str, user_id: Uuid) -> bool {
+            let name = name.to_lowercase();
+            self.users
+                .iter()
+                .any(|u| u.meta.id != user_id && u.meta.name.to_lowercase() == name)
+        }
+
+        /// Role of someone joining now, `None` when the room is full.
+        pub fn role_for_new_user(&self) -> Option<UserRole> {
+            if self.player_count() < self.limits.max_players as usize {
+                Some(UserRole::Player)
+            } else {
+                let has_spectator_spot = match self.limits.max_spectators {
+                    Some(max) => self.spectator_count() < max as usize,
+                    None => true,
+                };
+                has_spectator_spot.then_some(UserRole::Spectator)
+            }
+        }
+
+        /// Turns the longest waiting spectators into players while there's
+        /// room, returns their names. Spectators come right after the
+        /// players so the order of `users` holds.
+        pub fn promote_spectators(&mut self) -> Vec<String> {
+            let mut promoted = Vec::new();
+            while self.player_count() < self.limits.max_players as usize {
+                let Some(user) = self.users.iter_mut().find(|u| u.meta.role.is_spectator()) else {
+                    break;
+                };
+                user.meta.role = UserRole::Player;
+                promoted.push(user.meta.name.clone());
+            }
+            promoted
+        }
+
+        /// Adds `content` to the chat log, dropping the oldest message when
+        /// it's full, and returns the logged entry.
+        pub fn log_chat(&mut self, content: ChatContent) -> ChatEntry {
+            let entry = ChatEntry {
+                id: self.next_chat_id,
+                sent_at: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or_default(),
+                content,
+            };
+            self.next_chat_id += 1;
+            if self.chat_log.len() >= CHAT_LOG_LEN {
+                self.chat_log.pop_front();
             }
+            self.chat_log.push_back(entry.clone());
+            entry
         }
     }
     pub async fn get_rtc_info(username: &str) -> Result<message::RtcConfig, RoomProviderError> {
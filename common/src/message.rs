@@ -1,7 +1,9 @@
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use uuid::Uuid;
 
-use crate::{PlayerStatus, UserMeta};
+use crate::{PlayerStatus, Profile, UserMeta};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum Message {
@@ -14,6 +16,9 @@ pub enum Message {
 pub enum RTCMessage {
     ExchangeSessionDesc(Uuid, RTCSessionDesc),
     ExchangeCandidate(Uuid, String),
+    /// Bincode encoded input event, relayed through the room websocket when
+    /// the peer's data channel isn't open.
+    RelayInput(Uuid, Vec<u8>),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -24,6 +29,62 @@ pub enum ClientMessage {
     Seek(f64),
     Update(f64),
     Chat(String),
+    /// Client timestamp to be echoed back in a [`ServerMessage::Pong`], along
+    /// with the last measured round trip time in milliseconds.
+    Ping(f64, Option<u32>),
+    /// Virtual gamepad layout code the host recommends to the room.
+    RecommendLayout(String),
+    /// Position of the host's virtual cursor as a fraction of the game's
+    /// size, `None` once the mouse is used normally again.
+    GameCursor(Option<(f64, f64)>),
+    /// Where each player last pointed, sent by the host.
+    Cursors(Vec<UserCursor>),
+    /// `/me` action, logged in chat like a message.
+    Action(String),
+    /// Dice rolled by the server and logged in chat.
+    Roll(DiceRoll),
+    /// Removes a user from the room, host only.
+    Kick(Uuid),
+    /// New name, color and avatar, relayed to everyone once accepted.
+    UpdateProfile(Profile),
+    /// Player slot the user plays in, relayed to everyone once accepted.
+    ClaimSlot(Option<u8>),
+}
+
+/// `count` dice with `sides` sides each, like `2d6`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct DiceRoll {
+    pub count: u32,
+    pub sides: u32,
+}
+
+impl DiceRoll {
+    pub const MAX_COUNT: u32 = 10;
+    pub const MAX_SIDES: u32 = 1000;
+
+    pub fn is_valid(&self) -> bool {
+        (1..=Self::MAX_COUNT).contains(&self.count) && (2..=Self::MAX_SIDES).contains(&self.sides)
+    }
+
+    pub fn roll(&self) -> Vec<u32> {
+        let mut rng = rand::thread_rng();
+        (0..self.count)
+            .map(|_| rng.gen_range(1..=self.sides))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for DiceRoll {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)
+    }
+}
+
+/// Position of a user's mouse as a fraction of the game's size.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct UserCursor {
+    pub user_id: Uuid,
+    pub position: (f64, f64),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -32,6 +93,68 @@ pub enum ServerMessage {
     RoomJoined(RoomJoinInfo),
     UserJoined(UserJoined),
     UserLeft(UserLeft),
+    Pong(Pong),
+    /// Chat message logged by the server, sent to everyone in the room
+    /// including its author.
+    Chat(ChatEntry),
+    /// Something the user sent was rejected, sent to that user only.
+    Error(ServerError),
+    /// The host removed the user from the room, the connection is closed
+    /// right after.
+    Kicked,
+    /// Hosting or joining didn't work out, sent instead of [`ServerMessage::RoomCreated`]
+    /// or [`ServerMessage::RoomJoined`] and followed by a close with
+    /// [`crate::protocol::JOIN_FAILED_CLOSE_CODE`].
+    JoinFailed(JoinError),
+}
+
+/// Why a user couldn't host or join a room.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Error)]
+pub enum JoinError {
+    #[error("There is no room with that id")]
+    RoomNotFound,
+    #[error("The room is full")]
+    RoomFull,
+    #[error("Someone in the room already goes by that name")]
+    NameTaken,
+    #[error("A room with that code already exists")]
+    RoomCodeTaken,
+    #[error("That room code is reserved")]
+    RoomCodeReserved,
+    #[error("Something went wrong on the server, try again later")]
+    ServerError,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Error)]
+pub enum ServerError {
+    #[error("Message is too long, the limit is {max_len} characters")]
+    ChatTooLong { max_len: usize },
+    #[error("You are sending messages too fast")]
+    ChatRateLimited,
+    #[error("Message contains a blocked word")]
+    ChatBlocked,
+    #[error("Only the host can do that")]
+    NotHost,
+    #[error("No such user in the room")]
+    UserNotFound,
+    #[error("You can't kick yourself")]
+    KickSelf,
+    #[error("Names must be 1 to {max_len} characters long, without control characters")]
+    InvalidName { max_len: usize },
+    #[error("Someone in the room already goes by that name")]
+    NameTaken,
+    #[error("Colors must look like #ff8800")]
+    InvalidColor,
+    #[error("Avatars must be a single emoji")]
+    InvalidEmoji,
+    #[error("Player {0} is already taken")]
+    SlotTaken(u8),
+    #[error("Player slots go from 1 to {max}")]
+    InvalidSlot { max: u8 },
+    #[error("Can't roll that, try something like 2d6")]
+    InvalidRoll,
+    #[error("Spectators can't do that, wait for a player spot")]
+    Spectating,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -48,6 +171,14 @@ pub struct UserLeft {
     pub player_status: PlayerStatus,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Pong {
+    /// Timestamp sent by the client in its ping
+    pub sent_at: f64,
+    /// Latest reported latency of every user in the room
+    pub latencies: Vec<(Uuid, Option<u32>)>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct RoomJoinInfo {
     pub room_id: String,
@@ -55,6 +186,58 @@ pub struct RoomJoinInfo {
     pub users: Vec<UserMeta>,
     pub player_status: PlayerStatus,
     pub rtc_config: RtcConfig,
+    /// Last gamepad layout code recommended by the host
+    #[serde(default)]
+    pub recommended_layout: Option<String>,
+    /// Latest chat messages of the room, oldest first
+    #[serde(default)]
+    pub chat_log: Vec<ChatEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChatEntry {
+    /// Increases with every message logged in the room
+    pub id: u64,
+    /// Server time the message was logged at, in milliseconds since the unix epoch
+    pub sent_at: u64,
+    pub content: ChatContent,
+}
+
+/// What a chat entry says, system messages carry the name of the user they
+/// are about.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum ChatContent {
+    Message {
+        user_id: Uuid,
+        name: String,
+        text: String,
+    },
+    UserJoined(String),
+    UserLeft(String),
+    HostChanged(String),
+    Action {
+        user_id: Uuid,
+        name: String,
+        text: String,
+    },
+    Roll {
+        name: String,
+        roll: DiceRoll,
+        results: Vec<u32>,
+    },
+    UserKicked(String),
+    Renamed {
+        from: String,
+        to: String,
+    },
+    SlotClaimed {
+        name: String,
+        slot: Option<u8>,
+    },
+    /// Joined a full room, waiting for a player spot
+    SpectatorJoined(String),
+    /// Spectator who got the spot of a player that left
+    Promoted(String),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
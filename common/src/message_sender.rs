@@ -1,21 +1,36 @@
 use tracing::warn;
 
-use crate::message::Message;
+use crate::{
+    message::Message,
+    protocol::{self, Encoding},
+};
 
 pub trait MessageSender {
     #[allow(async_fn_in_trait)]
     async fn send_binary(&mut self, data: Vec<u8>);
 
     #[allow(async_fn_in_trait)]
-    async fn send_message(&mut self, message: &Message) {
-        let data = bincode::serialize(message);
-        match data {
-            Ok(data) => {
-                self.send_binary(data).await;
-            }
-            Err(err) => {
-                warn!("Failed to serialize message {err:#?}");
-            }
+    async fn send_text(&mut self, data: String);
+
+    #[allow(async_fn_in_trait)]
+    async fn send_message(&mut self, message: &Message, encoding: Encoding) {
+        match encoding {
+            Encoding::MessagePack => match protocol::encode(message) {
+                Ok(data) => {
+                    self.send_binary(data).await;
+                }
+                Err(err) => {
+                    warn!("Failed to serialize message {err:#?}");
+                }
+            },
+            Encoding::Json => match protocol::encode_json(message) {
+                Ok(data) => {
+                    self.send_text(data).await;
+                }
+                Err(err) => {
+                    warn!("Failed to serialize message {err:#?}");
+                }
+            },
         }
     }
 }
@@ -27,4 +42,10 @@ impl MessageSender for axum::extract::ws::WebSocket {
             warn!("Failed to send message {err:#?}")
         }
     }
+
+    async fn send_text(&mut self, data: String) {
+        if let Err(err) = self.send(axum::extract::ws::Message::Text(data)).await {
+            warn!("Failed to send message {err:#?}")
+        }
+    }
 }
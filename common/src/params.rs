@@ -1,12 +1,190 @@
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    message::ServerError, protocol::Encoding, Profile, RoomLimits, DEFAULT_MAX_PLAYERS,
+    MAX_PLAYERS, MAX_ROOM_ID_LEN, MAX_TITLE_LEN, MIN_ROOM_CODE_LEN,
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct HostParams {
     pub name: String,
+    /// Rest of the user's [`crate::Profile`]
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub emoji: Option<String>,
+    /// [`RoomLimits`] of the new room, defaults when missing
+    #[serde(default)]
+    pub max_players: Option<u8>,
+    #[serde(default)]
+    pub max_spectators: Option<u8>,
+    /// List the room in the room list under `title`
+    #[serde(default)]
+    pub public: bool,
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Code to use instead of a generated one
+    #[serde(default)]
+    pub room_code: Option<String>,
+    /// [`crate::protocol::PROTOCOL_VERSION`] of the client, missing for clients
+    /// that predate versioning.
+    #[serde(default)]
+    pub protocol_version: u32,
+    /// Encoding the client wants, a negotiated subprotocol takes precedence.
+    #[serde(default)]
+    pub encoding: Encoding,
+}
+
+impl HostParams {
+    /// Normalized profile of the connecting user.
+    pub fn profile(&self) -> Profile {
+        Profile {
+            name: self.name.clone(),
+            color: self.color.clone(),
+            emoji: self.emoji.clone(),
+        }
+        .normalized()
+    }
+
+    pub fn limits(&self) -> RoomLimits {
+        RoomLimits {
+            max_players: self.max_players.unwrap_or(DEFAULT_MAX_PLAYERS),
+            max_spectators: self.max_spectators,
+        }
+    }
+
+    /// Trimmed title of a public room, `None` for private ones.
+    pub fn public_title(&self) -> Option<String> {
+        self.public
+            .then(|| self.title.as_deref().unwrap_or_default().trim().to_string())
+    }
+
+    /// Trimmed lowercase code the host asked for, if any.
+    pub fn requested_room_code(&self) -> Option<String> {
+        self.room_code
+            .as_deref()
+            .map(|code| code.trim().to_lowercase())
+            .filter(|code| !code.is_empty())
+    }
+
+    pub fn validate(&self) -> Result<(), InvalidParams> {
+        let mut errors = profile_errors(&self.profile());
+        if let Some(code) = self.requested_room_code() {
+            if code.len() < MIN_ROOM_CODE_LEN
+                || !is_valid_room_id(&code)
+                || code.starts_with('-')
+                || code.ends_with('-')
+            {
+                errors.push(ParamError::InvalidRoomCode {
+                    min_len: MIN_ROOM_CODE_LEN,
+                    max_len: MAX_ROOM_ID_LEN,
+                });
+            }
+        }
+        if !(1..=MAX_PLAYERS).contains(&self.limits().max_players) {
+            errors.push(ParamError::InvalidMaxPlayers { max: MAX_PLAYERS });
+        }
+        if let Some(title) = self.public_title() {
+            if title.is_empty()
+                || title.chars().count() > MAX_TITLE_LEN
+                || title.chars().any(char::is_control)
+            {
+                errors.push(ParamError::InvalidTitle {
+                    max_len: MAX_TITLE_LEN,
+                });
+            }
+        }
+        InvalidParams::check(errors)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct JoinParams {
     pub name: String,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub emoji: Option<String>,
     pub room_id: String,
+    #[serde(default)]
+    pub protocol_version: u32,
+    #[serde(default)]
+    pub encoding: Encoding,
+}
+
+impl JoinParams {
+    /// Normalized profile of the connecting user.
+    pub fn profile(&self) -> Profile {
+        Profile {
+            name: self.name.clone(),
+            color: self.color.clone(),
+            emoji: self.emoji.clone(),
+        }
+        .normalized()
+    }
+
+    pub fn validate(&self) -> Result<(), InvalidParams> {
+        let mut errors = profile_errors(&self.profile());
+        if !is_valid_room_id(self.room_id.trim()) {
+            errors.push(ParamError::InvalidRoomId {
+                max_len: MAX_ROOM_ID_LEN,
+            });
+        }
+        InvalidParams::check(errors)
+    }
+}
+
+/// Problem with a single host or join param.
+#[derive(Error, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ParamError {
+    #[error("Room ids are 1 to {max_len} letters, digits or dashes")]
+    InvalidRoomId { max_len: usize },
+    #[error("Room codes are {min_len} to {max_len} letters, digits or dashes, not starting or ending with a dash")]
+    InvalidRoomCode { min_len: usize, max_len: usize },
+    #[error("Rooms take 1 to {max} players")]
+    InvalidMaxPlayers { max: u8 },
+    #[error("Public rooms need a title of 1 to {max_len} characters")]
+    InvalidTitle { max_len: usize },
+    /// What [`Profile::validate`] reports, serialized as the inner error
+    #[error(transparent)]
+    #[serde(untagged)]
+    Profile(#[from] ServerError),
+}
+
+/// Body of the 400 response to a host or join request with invalid params,
+/// holds every problem found.
+#[derive(Error, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[error("Invalid params {errors:?}")]
+pub struct InvalidParams {
+    pub errors: Vec<ParamError>,
+}
+
+impl InvalidParams {
+    fn check(errors: Vec<ParamError>) -> Result<(), Self> {
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Self { errors })
+        }
+    }
+}
+
+/// Whether `id` could be a room id, generated or picked by a host. Ids are
+/// compared case insensitively.
+pub fn is_valid_room_id(id: &str) -> bool {
+    (1..=MAX_ROOM_ID_LEN).contains(&id.len())
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn profile_errors(profile: &Profile) -> Vec<ParamError> {
+    [
+        profile.validate_name(),
+        profile.validate_color(),
+        profile.validate_emoji(),
+    ]
+    .into_iter()
+    .filter_map(Result::err)
+    .map(ParamError::from)
+    .collect()
 }
@@ -0,0 +1,90 @@
+//! Wire format of the room websocket.
+//!
+//! Messages are encoded as MessagePack with named fields, so a field added
+//! with `#[serde(default)]` is ignored by older peers and defaulted by newer
+//! ones. Enum variants are encoded by name, adding one or renaming or
+//! removing anything is not compatible and needs [`PROTOCOL_VERSION`] to be
+//! bumped, along with [`MIN_PROTOCOL_VERSION`] so older clients are turned
+//! away instead of failing to decode what they get.
+//!
+//! Clients that can't speak MessagePack can ask for [`Encoding::Json`]
+//! instead, the same [`crate::message::Message`] is then sent as JSON text
+//! frames. See `docs/PROTOCOL.md` for the schema.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+pub use rmp_serde::{decode::Error as DecodeError, encode::Error as EncodeError};
+
+/// Version of the protocol spoken by this build.
+pub const PROTOCOL_VERSION: u32 = 14;
+
+/// Oldest client protocol version the server still accepts, the last
+/// version that changed the wire format.
+pub const MIN_PROTOCOL_VERSION: u32 = 14;
+
+/// Websocket close code sent to clients with an incompatible protocol version.
+pub const INCOMPATIBLE_VERSION_CLOSE_CODE: u16 = 4000;
+
+/// Websocket close code sent after [`crate::message::ServerMessage::JoinFailed`].
+pub const JOIN_FAILED_CLOSE_CODE: u16 = 4001;
+
+/// Websocket subprotocol for [`Encoding::MessagePack`].
+pub const MSGPACK_SUBPROTOCOL: &str = "syncedflash.msgpack";
+/// Websocket subprotocol for [`Encoding::Json`].
+pub const JSON_SUBPROTOCOL: &str = "syncedflash.json";
+
+/// Encoding of the messages on a room websocket, chosen by the client with
+/// the `encoding` query param or the websocket subprotocol.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    /// Binary frames of named MessagePack
+    #[default]
+    #[serde(rename = "msgpack")]
+    MessagePack,
+    /// Text frames of JSON
+    Json,
+}
+
+impl Encoding {
+    pub fn from_subprotocol(protocol: &str) -> Option<Self> {
+        match protocol {
+            MSGPACK_SUBPROTOCOL => Some(Self::MessagePack),
+            JSON_SUBPROTOCOL => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    pub fn subprotocol(&self) -> &'static str {
+        match self {
+            Encoding::MessagePack => MSGPACK_SUBPROTOCOL,
+            Encoding::Json => JSON_SUBPROTOCOL,
+        }
+    }
+}
+
+pub fn is_compatible(version: u32) -> bool {
+    (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&version)
+}
+
+pub fn incompatible_version_reason(version: u32) -> String {
+    format!(
+        "This page is out of date (protocol v{version}, server v{PROTOCOL_VERSION}). Please refresh the page."
+    )
+}
+
+pub fn encode<T: Serialize>(val: &T) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    rmp_serde::to_vec_named(val)
+}
+
+pub fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T, rmp_serde::decode::Error> {
+    rmp_serde::from_slice(data)
+}
+
+pub fn encode_json<T: Serialize>(val: &T) -> Result<String, serde_json::Error> {
+    serde_json::to_string(val)
+}
+
+pub fn decode_json<T: DeserializeOwned>(data: &str) -> Result<T, serde_json::Error> {
+    serde_json::from_str(data)
+}
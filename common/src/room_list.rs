@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+use crate::RoomLimits;
+
+/// Room a host chose to list publicly, as served at
+/// [`crate::endpoints::ROOM_LIST`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PublicRoom {
+    pub room_id: String,
+    pub title: String,
+    /// File the host picked, if any
+    pub game: Option<String>,
+    pub players: usize,
+    pub spectators: usize,
+    pub limits: RoomLimits,
+    /// Seconds since the room was created
+    pub uptime_secs: u64,
+}
+
+impl PublicRoom {
+    /// Whether someone joining now would be turned away.
+    pub fn is_full(&self) -> bool {
+        self.players >= self.limits.max_players as usize
+            && self
+                .limits
+                .max_spectators
+                .is_some_and(|max| self.spectators >= max as usize)
+    }
+}
@@ -1,10 +1,9 @@
-use rand::{distributions::Alphanumeric, Rng};
+use rand::seq::SliceRandom;
 
-pub fn generate_random_string(length: usize) -> String {
+/// `length` chars picked at random from `alphabet`, which can't be empty.
+pub fn generate_random_string(length: usize, alphabet: &[char]) -> String {
     let mut rng = rand::thread_rng();
-    let result: String = std::iter::repeat(())
-        .map(|()| rng.sample(Alphanumeric) as char)
+    std::iter::repeat_with(|| *alphabet.choose(&mut rng).expect("empty alphabet"))
         .take(length)
-        .collect();
-    result.to_lowercase()
+        .collect()
 }
@@ -12,7 +12,10 @@ use leptos::*;
 use leptos_axum::{generate_route_list, handle_server_fns_with_context, LeptosRoutes};
 use leptos_router::RouteListing;
 use logging::warn;
-use room::{host_room, join_room};
+use room::{
+    heartbeat::HeartbeatConfig, host_room, join_room, moderation::ChatConfig,
+    room_code::RoomCodeConfig, room_list,
+};
 use tower_http::compression::CompressionLayer;
 use tracing::{info, level_filters::LevelFilter};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
@@ -25,6 +28,9 @@ pub struct AppState {
     leptos_options: LeptosOptions,
     routes: Vec<RouteListing>,
     pub rooms: RoomProvider,
+    pub heartbeat: HeartbeatConfig,
+    pub chat: ChatConfig,
+    pub room_codes: RoomCodeConfig,
 }
 
 #[tokio::main]
@@ -71,6 +77,9 @@ async fn main() {
         leptos_options,
         routes: routes.clone(),
         rooms: RoomProvider::new(),
+        heartbeat: HeartbeatConfig::from_env(),
+        chat: ChatConfig::from_env(),
+        room_codes: RoomCodeConfig::from_env(),
     };
     // build our application with a route
     let app = Router::new()
@@ -81,6 +90,7 @@ async fn main() {
         .leptos_routes_with_handler(routes, get(leptos_routes_handler))
         .route(endpoints::HOST_ROOM, get(host_room))
         .route(endpoints::JOIN_ROOM, get(join_room))
+        .route(endpoints::ROOM_LIST, get(room_list))
         .fallback(file_and_error_handler)
         .layer(compression)
         .with_state(app_state);
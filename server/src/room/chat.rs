@@ -0,0 +1,199 @@
+use common::{
+    message::{ChatContent, ClientMessage, Message, ServerError, ServerMessage},
+    MAX_SLOTS,
+};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::AppState;
+
+use super::rate_limit::RateLimiter;
+
+/// Logs `content` in the room's chat and sends it to everyone in the room.
+pub async fn post_chat(app_state: &AppState, room_id: &str, content: ChatContent) {
+    if let Some(entry) = app_state
+        .rooms
+        .with_room_mut(room_id, |room| room.log_chat(content))
+        .await
+    {
+        app_state
+            .rooms
+            .broadcast_msg_excluding(
+                room_id,
+                Message::ServerMessage(ServerMessage::Chat(entry)),
+                &[],
+            )
+            .await;
+    }
+}
+
+/// Handles chat messages and commands, what they did is logged in chat.
+/// `chat_limiter` is the sender's.
+pub async fn handle_chat(
+    app_state: &AppState,
+    room_id: &str,
+    user_id: Uuid,
+    message: &ClientMessage,
+    chat_limiter: &mut RateLimiter,
+) -> Result<(), ServerError> {
+    let (name, role) = app_state
+        .rooms
+        .with_room(room_id, |room| {
+            room.users
+                .iter()
+                .find(|u| u.meta.id == user_id)
+                .map(|u| (u.meta.name.clone(), u.meta.role))
+        })
+        .await
+        .flatten()
+        .ok_or(ServerError::UserNotFound)?;
+    let chat = &app_state.chat;
+    let content = match message {
+        ClientMessage::Chat(text) => {
+            chat.check(text, chat_limiter)?;
+            ChatContent::Message {
+                user_id,
+                name,
+                text: text.clone(),
+            }
+        }
+        ClientMessage::Action(text) => {
+            chat.check(text, chat_limiter)?;
+            ChatContent::Action {
+                user_id,
+                name,
+                text: text.clone(),
+            }
+        }
+        ClientMessage::Roll(roll) => {
+            if !roll.is_valid() {
+                return Err(ServerError::InvalidRoll);
+            }
+            chat.check_rate(chat_limiter)?;
+            ChatContent::Roll {
+                name,
+                roll: *roll,
+                results: roll.roll(),
+            }
+        }
+        ClientMessage::Kick(target) => {
+            chat.check_rate(chat_limiter)?;
+            ChatContent::UserKicked(kick(app_state, room_id, user_id, *target).await?)
+        }
+        ClientMessage::UpdateProfile(profile) => {
+            let profile = profile.clone().normalized();
+            profile.validate()?;
+            chat.check_words(&profile.name)?;
+            chat.check_rate(chat_limiter)?;
+            app_state
+                .rooms
+                .with_room_mut(room_id, |room| {
+                    if room.is_name_taken(&profile.name, user_id) {
+                        return Err(ServerError::NameTaken);
+                    }
+                    if let Some(user) = room.users.iter_mut().find(|u| u.meta.id == user_id) {
+                        user.meta.set_profile(profile.clone());
+                    }
+                    Ok(())
+                })
+                .await
+                .ok_or(ServerError::UserNotFound)??;
+            app_state
+                .rooms
+                .broadcast_msg_excluding(
+                    room_id,
+                    Message::ClientMessage((
+                        user_id,
+                        ClientMessage::UpdateProfile(profile.clone()),
+                    )),
+                    &[],
+                )
+                .await;
+            if profile.name == name {
+                return Ok(());
+            }
+            ChatContent::Renamed {
+                from: name,
+                to: profile.name,
+            }
+        }
+        ClientMessage::ClaimSlot(slot) => {
+            if role.is_spectator() {
+                return Err(ServerError::Spectating);
+            }
+            if slot.is_some_and(|slot| !(1..=MAX_SLOTS).contains(&slot)) {
+                return Err(ServerError::InvalidSlot { max: MAX_SLOTS });
+            }
+            chat.check_rate(chat_limiter)?;
+            app_state
+                .rooms
+                .with_room_mut(room_id, |room| {
+                    if let Some(slot) = slot {
+                        if room
+                            .users
+                            .iter()
+                            .any(|u| u.meta.id != user_id && u.meta.slot == Some(*slot))
+                        {
+                            return Err(ServerError::SlotTaken(*slot));
+                        }
+                    }
+                    if let Some(user) = room.users.iter_mut().find(|u| u.meta.id == user_id) {
+                        user.meta.slot = *slot;
+                    }
+                    Ok(())
+                })
+                .await
+                .ok_or(ServerError::UserNotFound)??;
+            app_state
+                .rooms
+                .broadcast_msg_excluding(
+                    room_id,
+                    Message::ClientMessage((user_id, ClientMessage::ClaimSlot(*slot))),
+                    &[],
+                )
+                .await;
+            ChatContent::SlotClaimed { name, slot: *slot }
+        }
+        _ => return Ok(()),
+    };
+    post_chat(app_state, room_id, content).await;
+    Ok(())
+}
+
+/// Removes `target` from the room if `user_id` is the host, returns the
+/// kicked user's name. Dropping their sender closes their websocket once
+/// [`ServerMessage::Kicked`] went through.
+async fn kick(
+    app_state: &AppState,
+    room_id: &str,
+    user_id: Uuid,
+    target: Uuid,
+) -> Result<String, ServerError> {
+    let kicked = app_state
+        .rooms
+        .with_room_mut(room_id, |room| {
+            if room.users.first().map(|u| u.meta.id) != Some(user_id) {
+                return Err(ServerError::NotHost);
+            }
+            if target == user_id {
+                return Err(ServerError::KickSelf);
+            }
+            let index = room
+                .users
+                .iter()
+                .position(|u| u.meta.id == target)
+                .ok_or(ServerError::UserNotFound)?;
+            Ok(room.users.remove(index))
+        })
+        .await
+        .ok_or(ServerError::UserNotFound)??;
+    info!("{user_id} kicked {target} from {room_id}");
+    if let Err(err) = kicked
+        .sender
+        .send(Message::ServerMessage(ServerMessage::Kicked))
+        .await
+    {
+        warn!("Failed to tell {target} they were kicked {err:?}");
+    }
+    Ok(kicked.meta.name)
+}
@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use super::number_from_env;
+
+const DEFAULT_INTERVAL_SECS: u64 = 10;
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// How often the server pings each client and how long it waits to hear
+/// anything back before dropping it from the room.
+#[derive(Debug, Clone, Copy)]
+pub struct HeartbeatConfig {
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+impl HeartbeatConfig {
+    /// Reads `HEARTBEAT_INTERVAL_SECS` and `HEARTBEAT_TIMEOUT_SECS`, falling back to defaults.
+    pub fn from_env() -> Self {
+        Self {
+            interval: Duration::from_secs(number_from_env(
+                "HEARTBEAT_INTERVAL_SECS",
+                DEFAULT_INTERVAL_SECS,
+            )),
+            timeout: Duration::from_secs(number_from_env(
+                "HEARTBEAT_TIMEOUT_SECS",
+                DEFAULT_TIMEOUT_SECS,
+            )),
+        }
+    }
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(DEFAULT_INTERVAL_SECS),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+        }
+    }
+}
@@ -1,25 +1,89 @@
+use app::utils::keycode::is_release_input;
 use axum::{
-    extract::{ws::WebSocket, Query, State, WebSocketUpgrade},
+    extract::{
+        ws::{CloseFrame, WebSocket},
+        Query, State, WebSocketUpgrade,
+    },
     http::StatusCode,
     response::{IntoResponse, Response},
+    Json,
 };
 use common::{
-    message::{Message, UserJoined, UserLeft},
+    message::{ChatContent, JoinError, Message, Pong, ServerMessage, UserJoined, UserLeft},
     message_sender::MessageSender,
-    params::{HostParams, JoinParams},
-    PlayerStatus, RoomProviderError, User, UserMeta, UserState,
+    params::{HostParams, InvalidParams, JoinParams},
+    protocol::{self, Encoding},
+    room_list::PublicRoom,
+    NewRoom, PlayerStatus, Profile, RoomProviderError, User, UserMeta, UserRole, UserState,
 };
 use leptos::logging::warn;
+use std::{fmt::Display, str::FromStr, time::Instant};
 use thiserror::Error;
 use tracing::info;
 use uuid::Uuid;
 
 use crate::AppState;
 
+use self::{chat::post_chat, moderation::ChatConfig, rate_limit::RateLimiter};
+
+pub mod chat;
+pub mod heartbeat;
+pub mod moderation;
+pub mod rate_limit;
+pub mod room_code;
+
+/// Input relayed over the websocket is allowed to burst up to this many events.
+const INPUT_RELAY_BURST: u32 = 240;
+/// Sustained rate of relayed input events per second for a single connection.
+const INPUT_RELAY_PER_SEC: u32 = 120;
+/// Largest gamepad layout code the host may recommend, in bytes.
+const MAX_LAYOUT_CODE_LEN: usize = 16 * 1024;
+
+/// Per connection state that lives as long as the websocket.
+pub struct ConnectionState {
+    pub session_id: Option<String>,
+    /// Encoding negotiated with the client, used for everything sent to it
+    pub encoding: Encoding,
+    pub input_limiter: RateLimiter,
+    pub chat_limiter: RateLimiter,
+    /// Last time anything, including pongs, was received from the client
+    pub last_seen: Instant,
+}
+
+impl ConnectionState {
+    pub fn new(encoding: Encoding, chat: &ChatConfig) -> Self {
+        Self {
+            session_id: None,
+            encoding,
+            input_limiter: RateLimiter::new(INPUT_RELAY_BURST, INPUT_RELAY_PER_SEC),
+            chat_limiter: chat.rate_limiter(),
+            last_seen: Instant::now(),
+        }
+    }
+}
+
+/// Positive number from the environment variable `key`, `default` when it's
+/// unset or invalid.
+pub(crate) fn number_from_env<T>(key: &str, default: T) -> T
+where
+    T: FromStr + PartialOrd + Default + Display,
+{
+    match std::env::var(key) {
+        Ok(val) => match val.parse::<T>() {
+            Ok(number) if number > T::default() => number,
+            _ => {
+                tracing::warn!("Invalid {key} {val:?}, using {default}");
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum RoomJoinError {
     #[error(transparent)]
-    RoomProviderError(#[from] RoomProviderError),
+    InvalidParams(#[from] InvalidParams),
 }
 
 #[axum::debug_handler]
@@ -28,25 +92,52 @@ pub async fn host_room(
     Query(host_params): Query<HostParams>,
     ws: WebSocketUpgrade,
 ) -> Result<Response, RoomJoinError> {
+    if !protocol::is_compatible(host_params.protocol_version) {
+        return Ok(reject_incompatible_client(ws, host_params.protocol_version));
+    }
+    host_params.validate()?;
+    let requested_id = host_params.requested_room_code();
+    if requested_id
+        .as_deref()
+        .is_some_and(|code| app_state.room_codes.is_reserved(code))
+    {
+        return Ok(reject_join(
+            ws,
+            host_params.encoding,
+            JoinError::RoomCodeReserved,
+        ));
+    }
     let (tx, rx) = tokio::sync::mpsc::channel(10);
     let user_id = Uuid::new_v4();
     let user = User {
-        meta: UserMeta {
-            id: user_id,
-            name: host_params.name,
-            state: common::UserState::VideoNotSelected,
-        },
+        meta: new_user_meta(user_id, host_params.profile()),
         sender: tx,
     };
-    let room_id = app_state.rooms.new_room(user).await?;
+    let new_room = NewRoom {
+        limits: host_params.limits(),
+        public_title: host_params.public_title(),
+        requested_id,
+    };
+    let room_codes = app_state.room_codes.clone();
+    let room_id = match app_state
+        .rooms
+        .new_room(user, new_room, || room_codes.generate())
+        .await
+    {
+        Ok(room_id) => room_id,
+        Err(err) => return Ok(reject_join(ws, host_params.encoding, join_error(err))),
+    };
 
+    let ws = with_subprotocols(ws);
     Ok(ws.on_upgrade(move |mut msgs| async move {
-        msgs.send_message(&Message::ServerMessage(
-            common::message::ServerMessage::RoomCreated(room_id.clone()),
-        ))
+        let encoding = negotiated_encoding(&msgs, host_params.encoding);
+        msgs.send_message(
+            &Message::ServerMessage(common::message::ServerMessage::RoomCreated(room_id.clone())),
+            encoding,
+        )
         .await;
 
-        handle_websocket(app_state, &room_id.room_id, user_id, msgs, rx).await;
+        handle_websocket(app_state, &room_id.room_id, user_id, msgs, rx, encoding).await;
     }))
 }
 
@@ -56,23 +147,23 @@ pub async fn join_room(
     Query(join_params): Query<JoinParams>,
     ws: WebSocketUpgrade,
 ) -> Result<Response, RoomJoinError> {
+    if !protocol::is_compatible(join_params.protocol_version) {
+        return Ok(reject_incompatible_client(ws, join_params.protocol_version));
+    }
+    join_params.validate()?;
     let (tx, rx) = tokio::sync::mpsc::channel(10); // 10 is random here.
     let user_id = Uuid::new_v4();
     let user = User {
-        meta: UserMeta {
-            id: user_id,
-            name: join_params.name,
-
-            state: common::UserState::VideoNotSelected,
-        },
+        meta: new_user_meta(user_id, join_params.profile()),
         sender: tx,
     };
 
-    let join_info = app_state
-        .rooms
-        .join_room(&join_params.room_id.to_lowercase(), user)
-        .await?;
-    let room_id = join_params.room_id;
+    let name = user.meta.name.clone();
+    let room_id = join_params.room_id.trim().to_lowercase();
+    let join_info = match app_state.rooms.join_room(&room_id, user).await {
+        Ok(join_info) => join_info,
+        Err(err) => return Ok(reject_join(ws, join_params.encoding, join_error(err))),
+    };
     if let Some(player_status) = app_state.rooms.get_room_player_status(&room_id).await {
         app_state
             .rooms
@@ -87,24 +178,144 @@ pub async fn join_room(
             )
             .await;
     }
+    let is_spectator = join_info
+        .users
+        .iter()
+        .any(|u| u.id == join_info.user_id && u.role.is_spectator());
+    let content = if is_spectator {
+        ChatContent::SpectatorJoined(name)
+    } else {
+        ChatContent::UserJoined(name)
+    };
+    post_chat(&app_state, &room_id, content).await;
+    let requested_encoding = join_params.encoding;
+    let ws = with_subprotocols(ws);
     Ok(ws.on_upgrade(move |mut msgs| async move {
-        msgs.send_message(&Message::ServerMessage(
-            common::message::ServerMessage::RoomJoined(join_info),
-        ))
+        let encoding = negotiated_encoding(&msgs, requested_encoding);
+        msgs.send_message(
+            &Message::ServerMessage(common::message::ServerMessage::RoomJoined(join_info)),
+            encoding,
+        )
         .await;
 
-        handle_websocket(app_state, &room_id, user_id, msgs, rx).await;
+        handle_websocket(app_state, &room_id, user_id, msgs, rx, encoding).await;
     }))
 }
 
+/// Public rooms for the home page, private ones are left out.
+pub async fn room_list(State(app_state): State<AppState>) -> Json<Vec<PublicRoom>> {
+    Json(app_state.rooms.public_rooms().await)
+}
+
+/// Meta of a user connecting with an already validated `profile`.
+fn new_user_meta(id: Uuid, profile: Profile) -> UserMeta {
+    UserMeta {
+        id,
+        name: profile.name,
+        state: UserState::VideoNotSelected,
+        latency: None,
+        slot: None,
+        color: profile.color,
+        emoji: profile.emoji,
+        role: UserRole::default(),
+    }
+}
+
+async fn is_spectator(app_state: &AppState, room_id: &str, user_id: Uuid) -> bool {
+    app_state
+        .rooms
+        .with_room(room_id, |room| {
+            room.users
+                .iter()
+                .any(|u| u.meta.id == user_id && u.meta.role.is_spectator())
+        })
+        .await
+        .unwrap_or(false)
+}
+
+/// Offers the protocol subprotocols, the first one the client also lists is accepted.
+fn with_subprotocols(ws: WebSocketUpgrade) -> WebSocketUpgrade {
+    ws.protocols([protocol::MSGPACK_SUBPROTOCOL, protocol::JSON_SUBPROTOCOL])
+}
+
+/// Encoding of an upgraded socket, the subprotocol wins over the query param.
+fn negotiated_encoding(socket: &WebSocket, requested: Encoding) -> Encoding {
+    socket
+        .protocol()
+        .and_then(|protocol| protocol.to_str().ok())
+        .and_then(Encoding::from_subprotocol)
+        .unwrap_or(requested)
+}
+
+/// Accepts the upgrade only to close it with a reason the client can show,
+/// a failed upgrade isn't visible to browser code.
+fn reject_incompatible_client(ws: WebSocketUpgrade, protocol_version: u32) -> Response {
+    info!("Rejecting client with protocol version {protocol_version}");
+    ws.on_upgrade(move |mut socket| async move {
+        if let Err(err) = socket
+            .send(axum::extract::ws::Message::Close(Some(CloseFrame {
+                code: protocol::INCOMPATIBLE_VERSION_CLOSE_CODE,
+                reason: protocol::incompatible_version_reason(protocol_version).into(),
+            })))
+            .await
+        {
+            warn!("Failed to close incompatible client {err:#?}");
+        }
+    })
+}
+
+/// What the client is told when the room provider refuses to host or join.
+fn join_error(err: RoomProviderError) -> JoinError {
+    match err {
+        RoomProviderError::RoomDoesntExist => JoinError::RoomNotFound,
+        RoomProviderError::RoomFull => JoinError::RoomFull,
+        RoomProviderError::RoomIdTaken => JoinError::RoomCodeTaken,
+        RoomProviderError::NameTaken => JoinError::NameTaken,
+        RoomProviderError::KeyGenerationFailed
+        | RoomProviderError::RTCConfigGenerationFailed(_)
+        | RoomProviderError::TimeError(_)
+        | RoomProviderError::HmacError(_) => {
+            warn!("Failed to set up room {err:#?}");
+            JoinError::ServerError
+        }
+    }
+}
+
+/// Accepts the upgrade only to tell the client why it can't host or join,
+/// like [`reject_incompatible_client`].
+fn reject_join(ws: WebSocketUpgrade, requested: Encoding, join_error: JoinError) -> Response {
+    info!("Rejecting join {join_error:?}");
+    with_subprotocols(ws).on_upgrade(move |mut socket| async move {
+        let encoding = negotiated_encoding(&socket, requested);
+        socket
+            .send_message(
+                &Message::ServerMessage(ServerMessage::JoinFailed(join_error.clone())),
+                encoding,
+            )
+            .await;
+        if let Err(err) = socket
+            .send(axum::extract::ws::Message::Close(Some(CloseFrame {
+                code: protocol::JOIN_FAILED_CLOSE_CODE,
+                reason: join_error.to_string().into(),
+            })))
+            .await
+        {
+            warn!("Failed to close rejected client {err:#?}");
+        }
+    })
+}
+
 async fn handle_websocket(
     app_state: AppState,
     room_id: &str,
     user_id: Uuid,
     mut socket: WebSocket,
     mut rx: tokio::sync::mpsc::Receiver<Message>,
+    encoding: Encoding,
 ) {
-    let mut session_id = None;
+    let mut connection_state = ConnectionState::new(encoding, &app_state.chat);
+    let mut heartbeat = tokio::time::interval(app_state.heartbeat.interval);
+    heartbeat.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
     loop {
         tokio::select! {
             msg = socket.recv() => {
@@ -112,7 +323,8 @@ async fn handle_websocket(
                     Some(msg) => {
                         match msg {
                             Ok(msg) => {
-                                if handle_message(msg, user_id, room_id, &app_state, &mut session_id, &mut socket).await {
+                                connection_state.last_seen = Instant::now();
+                                if handle_message(msg, user_id, room_id, &app_state, &mut connection_state, &mut socket).await {
                                     break;
                                 }
                             }
@@ -131,7 +343,13 @@ async fn handle_websocket(
             msg = rx.recv() => {
                 match msg {
                     Some(msg) => {
-                        socket.send_message(&msg).await;
+                        // A client that stopped reading fills its send buffer
+                        // and would block the heartbeat forever
+                        let send = socket.send_message(&msg, connection_state.encoding);
+                        if tokio::time::timeout(app_state.heartbeat.timeout, send).await.is_err() {
+                            info!("Sending timed out disconnecting {user_id}");
+                            break;
+                        }
                     }
                     None => {
                         // Sender dropped, room closed?
@@ -140,11 +358,51 @@ async fn handle_websocket(
                     }
                 }
             }
+            _ = heartbeat.tick() => {
+                if connection_state.last_seen.elapsed() > app_state.heartbeat.timeout {
+                    info!("Heartbeat timed out disconnecting {user_id}");
+                    break;
+                }
+                let ping = socket.send(axum::extract::ws::Message::Ping(Vec::new()));
+                match tokio::time::timeout(app_state.heartbeat.timeout, ping).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(err)) => {
+                        warn!("Failed to send ping disconnecting {user_id} {err:#?}");
+                        break;
+                    }
+                    Err(_) => {
+                        info!("Sending ping timed out disconnecting {user_id}");
+                        break;
+                    }
+                }
+            }
         }
     }
+    let leaving = app_state
+        .rooms
+        .with_room(room_id, |room| {
+            let index = room.users.iter().position(|u| u.meta.id == user_id)?;
+            Some((index == 0, room.users[index].meta.name.clone()))
+        })
+        .await
+        .flatten();
     let remaining_users = app_state.rooms.remove_user(room_id, user_id).await;
     info!("Disconnected user {user_id}");
-    if let Some(users) = remaining_users {
+    if let Some((users, promoted)) = remaining_users {
+        if let Some((was_host, name)) = leaving {
+            post_chat(&app_state, room_id, ChatContent::UserLeft(name)).await;
+            if let (true, Some(host)) = (was_host, users.first()) {
+                post_chat(
+                    &app_state,
+                    room_id,
+                    ChatContent::HostChanged(host.name.clone()),
+                )
+                .await;
+            }
+        }
+        for name in promoted {
+            post_chat(&app_state, room_id, ChatContent::Promoted(name)).await;
+        }
         if let Some(player_status) = app_state.rooms.get_room_player_status(room_id).await {
             app_state
                 .rooms
@@ -167,164 +425,256 @@ pub async fn handle_message(
     user_id: Uuid,
     room_id: &str,
     app_state: &AppState,
-    session_id: &mut Option<String>,
+    connection_state: &mut ConnectionState,
     socker: &mut WebSocket,
 ) -> bool {
-    match msg {
-        axum::extract::ws::Message::Text(_) => {
-            //ignore
+    let data = match msg {
+        axum::extract::ws::Message::Text(text) => {
+            protocol::decode_json::<Message>(&text).map_err(|err| format!("{err:#?}"))
         }
         axum::extract::ws::Message::Binary(data) => {
-            let data = bincode::deserialize::<Message>(&data[..]);
-            match data {
-                Ok(original_message) => {
-                    match &original_message {
-                        Message::ServerMessage(_) => {
-                            //ignore
+            protocol::decode::<Message>(&data[..]).map_err(|err| format!("{err:#?}"))
+        }
+        axum::extract::ws::Message::Ping(_) | axum::extract::ws::Message::Pong(_) => {
+            return false;
+        }
+        axum::extract::ws::Message::Close(_) => {
+            info!("Received Close from socket disconnecting {user_id}");
+            return true;
+        }
+    };
+    match data {
+        Ok(original_message) => {
+            match &original_message {
+                Message::ServerMessage(_) => {
+                    //ignore
+                }
+                // Spectators don't get a peer connection with the host
+                Message::RTCMessage(_) if is_spectator(app_state, room_id, user_id).await => {}
+                Message::RTCMessage(message) => match message {
+                    common::message::RTCMessage::ExchangeSessionDesc(uuid, rtcsession_desc) => {
+                        app_state
+                            .rooms
+                            .send_msg_for_user(
+                                room_id,
+                                *uuid,
+                                Message::RTCMessage(
+                                    common::message::RTCMessage::ExchangeSessionDesc(
+                                        user_id,
+                                        rtcsession_desc.clone(),
+                                    ),
+                                ),
+                            )
+                            .await;
+                    }
+                    common::message::RTCMessage::ExchangeCandidate(uuid, candidate) => {
+                        app_state
+                            .rooms
+                            .send_msg_for_user(
+                                room_id,
+                                *uuid,
+                                Message::RTCMessage(
+                                    common::message::RTCMessage::ExchangeCandidate(
+                                        user_id,
+                                        candidate.clone(),
+                                    ),
+                                ),
+                            )
+                            .await;
+                    }
+                    common::message::RTCMessage::RelayInput(uuid, input) => {
+                        // Only presses and moves are dropped when over the limit
+                        if !connection_state.input_limiter.try_acquire() && !is_release_input(input)
+                        {
+                            return false;
+                        }
+                        let is_host = app_state
+                            .rooms
+                            .with_room(room_id, |room| {
+                                room.users.first().map(|u| u.meta.id) == Some(*uuid)
+                            })
+                            .await
+                            .unwrap_or(false);
+                        if is_host {
+                            app_state
+                                .rooms
+                                .send_msg_for_user(
+                                    room_id,
+                                    *uuid,
+                                    Message::RTCMessage(common::message::RTCMessage::RelayInput(
+                                        user_id,
+                                        input.clone(),
+                                    )),
+                                )
+                                .await;
                         }
-                        Message::RTCMessage(message) => match message {
-                            common::message::RTCMessage::ExchangeSessionDesc(
-                                uuid,
-                                rtcsession_desc,
-                            ) => {
+                    }
+                },
+                Message::ClientMessage((sender_id, message)) => {
+                    if sender_id == &user_id {
+                        match message {
+                            common::message::ClientMessage::Chat(_)
+                            | common::message::ClientMessage::Action(_)
+                            | common::message::ClientMessage::Roll(_)
+                            | common::message::ClientMessage::Kick(_)
+                            | common::message::ClientMessage::UpdateProfile(_)
+                            | common::message::ClientMessage::ClaimSlot(_) => {
+                                if let Err(err) = chat::handle_chat(
+                                    app_state,
+                                    room_id,
+                                    user_id,
+                                    message,
+                                    &mut connection_state.chat_limiter,
+                                )
+                                .await
+                                {
+                                    info!("Rejecting chat from {user_id}: {err}");
+                                    socker
+                                        .send_message(
+                                            &Message::ServerMessage(ServerMessage::Error(err)),
+                                            connection_state.encoding,
+                                        )
+                                        .await;
+                                }
+                            }
+                            common::message::ClientMessage::SelectedVideo(video_name) => {
                                 app_state
                                     .rooms
-                                    .send_msg_for_user(
-                                        room_id,
-                                        *uuid,
-                                        Message::RTCMessage(
-                                            common::message::RTCMessage::ExchangeSessionDesc(
-                                                user_id,
-                                                rtcsession_desc.clone(),
-                                            ),
-                                        ),
-                                    )
+                                    .with_room_mut(room_id, |room| {
+                                        if let Some(user) =
+                                            room.users.iter_mut().find(|u| u.meta.id == user_id)
+                                        {
+                                            user.meta.state =
+                                                UserState::VideoSelected(video_name.clone());
+                                        }
+                                    })
+                                    .await;
+                                app_state
+                                    .rooms
+                                    .broadcast_msg_excluding(room_id, original_message, &[user_id])
                                     .await;
                             }
-                            common::message::RTCMessage::ExchangeCandidate(uuid, candidate) => {
+                            common::message::ClientMessage::Play(val) => {
                                 app_state
                                     .rooms
-                                    .send_msg_for_user(
-                                        room_id,
-                                        *uuid,
-                                        Message::RTCMessage(
-                                            common::message::RTCMessage::ExchangeCandidate(
-                                                user_id,
-                                                candidate.clone(),
-                                            ),
+                                    .with_room_mut(room_id, |room| {
+                                        room.player_status = PlayerStatus::Playing(*val);
+                                    })
+                                    .await;
+                                app_state
+                                    .rooms
+                                    .broadcast_msg_excluding(room_id, original_message, &[user_id])
+                                    .await;
+                            }
+                            common::message::ClientMessage::Pause(val) => {
+                                app_state
+                                    .rooms
+                                    .with_room_mut(room_id, |room| {
+                                        room.player_status = PlayerStatus::Paused(*val);
+                                    })
+                                    .await;
+                                app_state
+                                    .rooms
+                                    .broadcast_msg_excluding(room_id, original_message, &[user_id])
+                                    .await;
+                            }
+                            common::message::ClientMessage::Ping(sent_at, latency) => {
+                                let latencies = app_state
+                                    .rooms
+                                    .with_room_mut(room_id, |room| {
+                                        if let Some(user) =
+                                            room.users.iter_mut().find(|u| u.meta.id == user_id)
+                                        {
+                                            user.meta.latency = *latency;
+                                        }
+                                        room.users
+                                            .iter()
+                                            .map(|u| (u.meta.id, u.meta.latency))
+                                            .collect()
+                                    })
+                                    .await
+                                    .unwrap_or_default();
+                                socker
+                                    .send_message(
+                                        &Message::ServerMessage(
+                                            common::message::ServerMessage::Pong(Pong {
+                                                sent_at: *sent_at,
+                                                latencies,
+                                            }),
                                         ),
+                                        connection_state.encoding,
                                     )
                                     .await;
                             }
-                        },
-                        Message::ClientMessage((sender_id, message)) => {
-                            if sender_id == &user_id {
-                                match message {
-                                    common::message::ClientMessage::Chat(_) => {
-                                        app_state
-                                            .rooms
-                                            .broadcast_msg_excluding(
-                                                room_id,
-                                                original_message,
-                                                &[user_id],
-                                            )
-                                            .await;
-                                    }
-                                    common::message::ClientMessage::SelectedVideo(video_name) => {
-                                        app_state
-                                            .rooms
-                                            .with_room_mut(room_id, |room| {
-                                                if let Some(user) = room
-                                                    .users
-                                                    .iter_mut()
-                                                    .find(|u| u.meta.id == user_id)
-                                                {
-                                                    user.meta.state = UserState::VideoSelected(
-                                                        video_name.clone(),
-                                                    );
-                                                }
-                                            })
-                                            .await;
-                                        app_state
-                                            .rooms
-                                            .broadcast_msg_excluding(
-                                                room_id,
-                                                original_message,
-                                                &[user_id],
-                                            )
-                                            .await;
-                                    }
-                                    common::message::ClientMessage::Play(val) => {
-                                        app_state
-                                            .rooms
-                                            .with_room_mut(room_id, |room| {
-                                                room.player_status = PlayerStatus::Playing(*val);
-                                            })
-                                            .await;
-                                        app_state
-                                            .rooms
-                                            .broadcast_msg_excluding(
-                                                room_id,
-                                                original_message,
-                                                &[user_id],
-                                            )
-                                            .await;
-                                    }
-                                    common::message::ClientMessage::Pause(val) => {
-                                        app_state
-                                            .rooms
-                                            .with_room_mut(room_id, |room| {
-                                                room.player_status = PlayerStatus::Paused(*val);
-                                            })
-                                            .await;
-                                        app_state
-                                            .rooms
-                                            .broadcast_msg_excluding(
-                                                room_id,
-                                                original_message,
-                                                &[user_id],
-                                            )
-                                            .await;
-                                    }
-                                    common::message::ClientMessage::Seek(val)
-                                    | common::message::ClientMessage::Update(val) => {
-                                        app_state
-                                            .rooms
-                                            .with_room_mut(room_id, |room| {
-                                                match &mut room.player_status {
-                                                    PlayerStatus::Paused(time)
-                                                    | PlayerStatus::Playing(time) => *time = *val,
-                                                }
-                                            })
-                                            .await;
-                                        app_state
-                                            .rooms
-                                            .broadcast_msg_excluding(
-                                                room_id,
-                                                original_message,
-                                                &[user_id],
-                                            )
-                                            .await;
-                                    }
+                            common::message::ClientMessage::RecommendLayout(code) => {
+                                if code.len() > MAX_LAYOUT_CODE_LEN {
+                                    warn!("Ignoring oversized layout from {user_id}");
+                                    return false;
+                                }
+                                let is_host = app_state
+                                    .rooms
+                                    .with_room_mut(room_id, |room| {
+                                        let is_host =
+                                            room.users.first().map(|u| u.meta.id) == Some(user_id);
+                                        if is_host {
+                                            room.recommended_layout = Some(code.clone());
+                                        }
+                                        is_host
+                                    })
+                                    .await
+                                    .unwrap_or_default();
+                                if is_host {
+                                    app_state
+                                        .rooms
+                                        .broadcast_msg_excluding(
+                                            room_id,
+                                            original_message,
+                                            &[user_id],
+                                        )
+                                        .await;
                                 }
                             }
+                            common::message::ClientMessage::GameCursor(_)
+                            | common::message::ClientMessage::Cursors(_) => {
+                                let is_host = app_state
+                                    .rooms
+                                    .with_room(room_id, |room| {
+                                        room.users.first().map(|u| u.meta.id) == Some(user_id)
+                                    })
+                                    .await
+                                    .unwrap_or_default();
+                                if is_host {
+                                    app_state
+                                        .rooms
+                                        .broadcast_msg_excluding(
+                                            room_id,
+                                            original_message,
+                                            &[user_id],
+                                        )
+                                        .await;
+                                }
+                            }
+                            common::message::ClientMessage::Seek(val)
+                            | common::message::ClientMessage::Update(val) => {
+                                app_state
+                                    .rooms
+                                    .with_room_mut(room_id, |room| match &mut room.player_status {
+                                        PlayerStatus::Paused(time)
+                                        | PlayerStatus::Playing(time) => *time = *val,
+                                    })
+                                    .await;
+                                app_state
+                                    .rooms
+                                    .broadcast_msg_excluding(room_id, original_message, &[user_id])
+                                    .await;
+                            }
                         }
                     }
                 }
-                Err(err) => {
-                    warn!("Received msg decode error {err:#?}")
-                }
             }
         }
-        axum::extract::ws::Message::Ping(_) => {
-            //ignore
-        }
-        axum::extract::ws::Message::Pong(_) => {
-            //ignore
-        }
-        axum::extract::ws::Message::Close(_) => {
-            info!("Received Close from socket disconnecting {user_id}");
-            return true;
+        Err(err) => {
+            warn!("Received msg decode error {err}")
         }
     }
     return false;
@@ -333,17 +683,10 @@ pub async fn handle_message(
 impl IntoResponse for RoomJoinError {
     fn into_response(self) -> Response {
         match self {
-            RoomJoinError::RoomProviderError(err) => match err {
-                RoomProviderError::KeyGenerationFailed
-                | RoomProviderError::RTCConfigGenerationFailed(_)
-                | RoomProviderError::TimeError(_)
-                | RoomProviderError::HmacError(_) => {
-                    (StatusCode::INTERNAL_SERVER_ERROR, format!("{err:#?}")).into_response()
-                }
-                RoomProviderError::RoomDoesntExist => {
-                    (StatusCode::BAD_REQUEST, format!("{err:#?}")).into_response()
-                }
-            },
+            RoomJoinError::InvalidParams(invalid) => {
+                info!("Rejecting connection {invalid}");
+                (StatusCode::BAD_REQUEST, Json(invalid)).into_response()
+            }
         }
     }
 }
@@ -0,0 +1,90 @@
+use std::{collections::HashSet, sync::Arc};
+
+use common::message::ServerError;
+
+use super::{number_from_env, rate_limit::RateLimiter};
+
+const DEFAULT_MAX_LEN: usize = 500;
+const DEFAULT_BURST: u32 = 5;
+const DEFAULT_PER_SEC: u32 = 1;
+
+/// Limits on what users can post in chat.
+#[derive(Debug, Clone)]
+pub struct ChatConfig {
+    /// Longest message accepted, in characters
+    pub max_len: usize,
+    /// Messages a user can send in a row before being rate limited
+    pub burst: u32,
+    /// Sustained messages per second for a single user
+    pub per_sec: u32,
+    /// Lowercase words rejected anywhere in a message
+    pub blocked_words: Arc<HashSet<String>>,
+}
+
+impl ChatConfig {
+    /// Reads `CHAT_MAX_LEN`, `CHAT_BURST`, `CHAT_PER_SEC` and the comma
+    /// separated `CHAT_BLOCKED_WORDS`, falling back to defaults and no filter.
+    pub fn from_env() -> Self {
+        let blocked_words = std::env::var("CHAT_BLOCKED_WORDS")
+            .map(|words| {
+                words
+                    .split(',')
+                    .map(|word| word.trim().to_lowercase())
+                    .filter(|word| !word.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            max_len: number_from_env("CHAT_MAX_LEN", DEFAULT_MAX_LEN),
+            burst: number_from_env("CHAT_BURST", DEFAULT_BURST),
+            per_sec: number_from_env("CHAT_PER_SEC", DEFAULT_PER_SEC),
+            blocked_words: Arc::new(blocked_words),
+        }
+    }
+
+    pub fn rate_limiter(&self) -> RateLimiter {
+        RateLimiter::new(self.burst, self.per_sec)
+    }
+
+    /// Checks a message before it's logged, `limiter` is the sender's.
+    pub fn check(&self, text: &str, limiter: &mut RateLimiter) -> Result<(), ServerError> {
+        if text.chars().count() > self.max_len {
+            return Err(ServerError::ChatTooLong {
+                max_len: self.max_len,
+            });
+        }
+        self.check_rate(limiter)?;
+        self.check_words(text)
+    }
+
+    /// Takes a token for anything that ends up in chat.
+    pub fn check_rate(&self, limiter: &mut RateLimiter) -> Result<(), ServerError> {
+        if limiter.try_acquire() {
+            Ok(())
+        } else {
+            Err(ServerError::ChatRateLimited)
+        }
+    }
+
+    pub fn check_words(&self, text: &str) -> Result<(), ServerError> {
+        if !self.blocked_words.is_empty()
+            && text
+                .split(|c: char| !c.is_alphanumeric())
+                .any(|word| self.blocked_words.contains(&word.to_lowercase()))
+        {
+            return Err(ServerError::ChatBlocked);
+        }
+        Ok(())
+    }
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            max_len: DEFAULT_MAX_LEN,
+            burst: DEFAULT_BURST,
+            per_sec: DEFAULT_PER_SEC,
+            blocked_words: Arc::default(),
+        }
+    }
+}
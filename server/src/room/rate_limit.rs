@@ -0,0 +1,35 @@
+use std::time::Instant;
+
+/// Token bucket used to cap how fast a single connection can push messages
+/// through the server.
+pub struct RateLimiter {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(capacity: u32, refill_per_sec: u32) -> Self {
+        Self {
+            capacity: f64::from(capacity),
+            tokens: f64::from(capacity),
+            refill_per_sec: f64::from(refill_per_sec),
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Takes a token from the bucket, returns `false` if the bucket is empty.
+    pub fn try_acquire(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::{collections::HashSet, sync::Arc};
+
+use common::{params::is_valid_room_id, util::generate_random_string, MAX_ROOM_ID_LEN};
+use tracing::warn;
+
+use super::number_from_env;
+
+const DEFAULT_LEN: usize = 6;
+/// Lowercase letters and digits without the easily confused `i`, `l`, `o`,
+/// `0` and `1`.
+const DEFAULT_ALPHABET: &str = "abcdefghjkmnpqrstuvwxyz23456789";
+/// Shortest generated code allowed, shorter ones are too easy to guess.
+const MIN_LEN: usize = 4;
+/// Generated codes tried before settling for a reserved one, the room
+/// provider still checks for collisions.
+const GENERATE_TRIES: usize = 10;
+
+/// How room codes are generated and which ones hosts can't pick.
+#[derive(Debug, Clone)]
+pub struct RoomCodeConfig {
+    /// Length of generated codes
+    pub len: usize,
+    /// Chars generated codes are made of
+    pub alphabet: Arc<[char]>,
+    /// Lowercase codes hosts can't pick and that aren't generated
+    pub reserved: Arc<HashSet<String>>,
+}
+
+impl RoomCodeConfig {
+    /// Reads `ROOM_CODE_LEN`, `ROOM_CODE_ALPHABET` and the comma separated
+    /// `RESERVED_ROOM_CODES`, falling back to defaults and nothing reserved.
+    pub fn from_env() -> Self {
+        let mut len = number_from_env("ROOM_CODE_LEN", DEFAULT_LEN);
+        if !(MIN_LEN..=MAX_ROOM_ID_LEN).contains(&len) {
+            warn!("ROOM_CODE_LEN must be {MIN_LEN} to {MAX_ROOM_ID_LEN}, using {DEFAULT_LEN}");
+            len = DEFAULT_LEN;
+        }
+        let alphabet = match std::env::var("ROOM_CODE_ALPHABET") {
+            Ok(alphabet) => {
+                let mut chars = alphabet.to_lowercase().chars().collect::<Vec<_>>();
+                chars.sort_unstable();
+                chars.dedup();
+                if chars.len() >= 2 && chars.iter().all(char::is_ascii_alphanumeric) {
+                    chars
+                } else {
+                    warn!(
+                        "ROOM_CODE_ALPHABET needs 2 or more letters or digits, using the default"
+                    );
+                    DEFAULT_ALPHABET.chars().collect()
+                }
+            }
+            Err(_) => DEFAULT_ALPHABET.chars().collect(),
+        };
+        let reserved = std::env::var("RESERVED_ROOM_CODES")
+            .map(|codes| {
+                codes
+                    .split(',')
+                    .map(|code| code.trim().to_lowercase())
+                    .filter(|code| is_valid_room_id(code))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            len,
+            alphabet: alphabet.into(),
+            reserved: Arc::new(reserved),
+        }
+    }
+
+    pub fn is_reserved(&self, code: &str) -> bool {
+        self.reserved.contains(&code.to_lowercase())
+    }
+
+    /// Random code that isn't reserved.
+    pub fn generate(&self) -> String {
+        let mut code = generate_random_string(self.len, &self.alphabet);
+        for _ in 1..GENERATE_TRIES {
+            if !self.is_reserved(&code) {
+                break;
+            }
+            code = generate_random_string(self.len, &self.alphabet);
+        }
+        code
+    }
+}
+
+impl Default for RoomCodeConfig {
+    fn default() -> Self {
+        Self {
+            len: DEFAULT_LEN,
+            alphabet: DEFAULT_ALPHABET.chars().collect::<Vec<_>>().into(),
+            reserved: Arc::default(),
+        }
+    }
+}